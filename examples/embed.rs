@@ -0,0 +1,61 @@
+// Drives the renderer from an event loop of its own: `cargo run --example
+// embed [SHADER]` opens a window and animates SHADER, or the built-in pattern
+use shader::{Options, RenderError, ShaderApp};
+use std::time::Instant;
+use winit::event::{Event, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::window::WindowBuilder;
+
+fn main() {
+    let options = Options {
+        shader_path: std::env::args().nth(1).map(Into::into),
+        ..Options::default()
+    };
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_title("Embedded shader")
+        .build(&event_loop)
+        .expect("failed to create the window");
+    let mut app = match ShaderApp::new(&event_loop, window, options) {
+        Ok(app) => app,
+        Err(err) => {
+            eprintln!("Failed to set up the renderer: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let start = Instant::now();
+    event_loop.run(move |event, target, control_flow| {
+        *control_flow = ControlFlow::Poll;
+        match event {
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                WindowEvent::Resized(size) => app.resize(size.width, size.height),
+                _ => {}
+            },
+            Event::MainEventsCleared => app.window().request_redraw(),
+            Event::RedrawRequested(_) => {
+                let result = app
+                    .update(start.elapsed().as_secs_f32())
+                    .and_then(|()| app.render());
+                match result {
+                    Ok(()) => {}
+                    Err(RenderError::Surface(
+                        wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated,
+                    )) => {
+                        let size = app.window().inner_size();
+                        app.resize(size.width, size.height);
+                    }
+                    // Try again on the next frame until the driver is back
+                    Err(RenderError::DeviceLost) => {
+                        if let Err(err) = app.recover(target) {
+                            eprintln!("Failed to recover from the device loss: {}", err);
+                        }
+                    }
+                    Err(err) => eprintln!("Failed to render: {}", err),
+                }
+            }
+            _ => {}
+        }
+    });
+}
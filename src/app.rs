@@ -0,0 +1,133 @@
+use crate::{load_shader, FragmentSource, Gpu, Options, Uniforms};
+use std::fmt;
+use winit::dpi::PhysicalSize;
+use winit::event_loop::EventLoopWindowTarget;
+use winit::window::Window;
+
+// The renderer without the `shader` command around it, for drawing into a
+// window from another program's event loop. It's set up from `Options` the
+// way the command line is, SHADER included, but input and timing are up to
+// the caller, and only the shader is drawn, without the overlay or panel.
+pub struct ShaderApp {
+    gpu: Gpu,
+    uniforms: Uniforms,
+    options: Options,
+    // Kept to rebuild everything from after a device loss
+    source: FragmentSource,
+    // Declared last so it's dropped after the surface drawing into it
+    window: Window,
+}
+
+// Why `ShaderApp` couldn't draw a frame
+#[derive(Debug)]
+pub enum RenderError {
+    // The surface needs reconfiguring with `resize`, or the frame skipping
+    Surface(wgpu::SurfaceError),
+    // Nothing can be drawn until `recover` builds a new device
+    DeviceLost,
+    // An error wgpu reported outside any error scope, already logged. The
+    // frame may be wrong, but drawing can go on.
+    Gpu(String),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RenderError::Surface(err) => write!(f, "{}", err),
+            RenderError::DeviceLost => write!(f, "the GPU device was lost"),
+            RenderError::Gpu(err) => write!(f, "wgpu error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+impl ShaderApp {
+    // `event_loop` is the one `window` belongs to
+    pub fn new(
+        event_loop: &EventLoopWindowTarget<()>,
+        window: Window,
+        options: Options,
+    ) -> Result<Self, String> {
+        let source = load_shader(&options)?;
+        let mut uniforms = Uniforms::configured(0, 0, &options);
+        uniforms.dpi = window.scale_factor() as f32 * options.scale;
+        let gpu = pollster::block_on(Gpu::new(
+            event_loop,
+            &window,
+            &options,
+            &source,
+            &mut uniforms,
+        ))?;
+        Ok(Self {
+            gpu,
+            uniforms,
+            options,
+            source,
+            window,
+        })
+    }
+
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+
+    // Replace the lost device and everything made with it, keeping the time
+    // and frame count. It can fail while the driver is still coming back,
+    // and can be tried again later.
+    pub fn recover(&mut self, event_loop: &EventLoopWindowTarget<()>) -> Result<(), String> {
+        self.gpu = pollster::block_on(Gpu::new(
+            event_loop,
+            &self.window,
+            &self.options,
+            &self.source,
+            &mut self.uniforms,
+        ))?;
+        Ok(())
+    }
+
+    // Match a new window size, in physical pixels. Calling it with the current
+    // size again recovers from a lost or outdated surface. A lost device is
+    // left alone, since `recover` sizes the new one to the window.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if self.gpu.errors.lost() {
+            return;
+        }
+        self.gpu.resize(
+            PhysicalSize::new(width, height),
+            self.options.scale,
+            &mut self.uniforms,
+        );
+    }
+
+    // Set the animation time of the next frame, in seconds, and upload it
+    pub fn update(&mut self, time: f32) -> Result<(), RenderError> {
+        self.uniforms.delta = (time - self.uniforms.time).max(0.0);
+        self.uniforms.time = time;
+        if self.gpu.errors.lost() {
+            return Err(RenderError::DeviceLost);
+        }
+        self.gpu.update(&self.uniforms);
+        Ok(())
+    }
+
+    // Draw a frame and present it
+    pub fn render(&mut self) -> Result<(), RenderError> {
+        if self.gpu.errors.lost() {
+            return Err(RenderError::DeviceLost);
+        }
+        self.gpu.poll();
+        self.gpu
+            .render(&self.window, &mut self.uniforms, false)
+            .map_err(RenderError::Surface)?;
+        self.uniforms.frame = self.uniforms.frame.wrapping_add(1);
+        // The device may have been lost while drawing
+        if self.gpu.errors.lost() {
+            return Err(RenderError::DeviceLost);
+        }
+        match self.gpu.errors.take() {
+            Some(err) => Err(RenderError::Gpu(err)),
+            None => Ok(()),
+        }
+    }
+}
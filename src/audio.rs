@@ -0,0 +1,144 @@
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SizedSample};
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::sync::{Arc, Mutex};
+
+// Samples per analysis window, about 20 ms at 48 kHz
+const FFT_SIZE: usize = 1024;
+
+// Frequency ranges of the bass, mid and treble bands, in Hz
+const BANDS: [(f32, f32); 3] = [(20.0, 250.0), (250.0, 4000.0), (4000.0, 16000.0)];
+
+// How much of the previous level is kept each frame, to steady the flicker
+const SMOOTHING: f32 = 0.8;
+
+// Listens to the default audio input and turns the latest samples into the
+// shader's `audio` uniform: the RMS level of the bass, mid and treble bands
+// and of the whole signal, so a full-scale sine reads about 0.7
+pub struct Audio {
+    // Capture stops when the stream is dropped
+    _stream: cpal::Stream,
+    // The most recent `FFT_SIZE` samples, mixed down to mono
+    samples: Arc<Mutex<VecDeque<f32>>>,
+    sample_rate: f32,
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    levels: [f32; 4],
+}
+
+impl Audio {
+    pub fn new() -> Result<Self, String> {
+        let device = cpal::default_host()
+            .default_input_device()
+            .ok_or_else(|| "no audio input device".to_string())?;
+        let config = device
+            .default_input_config()
+            .map_err(|err| err.to_string())?;
+
+        let samples = Arc::new(Mutex::new(VecDeque::with_capacity(FFT_SIZE)));
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::F32 => capture::<f32>(&device, &config.config(), &samples),
+            cpal::SampleFormat::I16 => capture::<i16>(&device, &config.config(), &samples),
+            cpal::SampleFormat::U16 => capture::<u16>(&device, &config.config(), &samples),
+            format => return Err(format!("unsupported sample format {}", format)),
+        }?;
+        stream.play().map_err(|err| err.to_string())?;
+
+        // Hann window, to keep loud bands from leaking into their neighbours
+        let window = (0..FFT_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / (FFT_SIZE - 1) as f32).cos())
+            .collect();
+
+        Ok(Self {
+            _stream: stream,
+            samples,
+            sample_rate: config.sample_rate().0 as f32,
+            fft: FftPlanner::new().plan_fft_forward(FFT_SIZE),
+            window,
+            levels: [0.0; 4],
+        })
+    }
+
+    // Analyse the latest samples and return bass, mid, treble and volume
+    pub fn levels(&mut self) -> [f32; 4] {
+        let mut buffer: Vec<_> = {
+            let samples = self.samples.lock().unwrap();
+            if samples.len() < FFT_SIZE {
+                return self.levels;
+            }
+            samples
+                .iter()
+                .zip(&self.window)
+                .map(|(sample, weight)| Complex::new(sample * weight, 0.0))
+                .collect()
+        };
+        let window_energy: f32 = self.window.iter().map(|weight| weight * weight).sum();
+        let volume = (buffer
+            .iter()
+            .map(|sample| sample.re * sample.re)
+            .sum::<f32>()
+            / window_energy)
+            .sqrt();
+
+        self.fft.process(&mut buffer);
+
+        // By Parseval's theorem the power in a range of bins, counting the
+        // mirrored negative frequencies too, is that part of the signal's
+        // windowed energy
+        let bin_width = self.sample_rate / FFT_SIZE as f32;
+        let mut current = [0.0, 0.0, 0.0, volume];
+        for (level, (low, high)) in current.iter_mut().zip(BANDS) {
+            let first = ((low / bin_width).ceil() as usize).max(1);
+            let last = ((high / bin_width) as usize).min(FFT_SIZE / 2);
+            if first >= last {
+                continue;
+            }
+            let power: f32 = buffer[first..last].iter().map(|bin| bin.norm_sqr()).sum();
+            *level = (2.0 * power / FFT_SIZE as f32 / window_energy).sqrt();
+        }
+
+        for (level, current) in self.levels.iter_mut().zip(current) {
+            *level = *level * SMOOTHING + current * (1.0 - SMOOTHING);
+        }
+        self.levels
+    }
+}
+
+// Open an input stream that mixes incoming frames down to mono and appends
+// them to `samples`, dropping the oldest beyond `FFT_SIZE`
+fn capture<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    samples: &Arc<Mutex<VecDeque<f32>>>,
+) -> Result<cpal::Stream, String>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = config.channels as usize;
+    let samples = samples.clone();
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _: &cpal::InputCallbackInfo| {
+                let mut samples = samples.lock().unwrap();
+                for frame in data.chunks(channels) {
+                    let mono = frame
+                        .iter()
+                        .map(|sample| sample.to_sample::<f32>())
+                        .sum::<f32>()
+                        / channels as f32;
+                    if samples.len() == FFT_SIZE {
+                        samples.pop_front();
+                    }
+                    samples.push_back(mono);
+                }
+            },
+            |err| log::warn!("Audio input error: {}", err),
+            None,
+        )
+        .map_err(|err| err.to_string())
+}
@@ -0,0 +1,80 @@
+use crate::options::{Bench, Options};
+use crate::timer::GpuTimer;
+use crate::{FragmentSource, Offscreen, HEADLESS_FPS};
+use std::time::Instant;
+
+// Render `bench.frames` frames offscreen as fast as possible and print frame
+// time statistics. Each frame is waited on before the next one starts, so the
+// CPU time covers the GPU work as well.
+pub fn bench(
+    options: &Options,
+    bench: &Bench,
+    fragment_source: &FragmentSource,
+) -> Result<(), String> {
+    let mut offscreen = Offscreen::new(options, fragment_source, bench.width, bench.height)?;
+    offscreen.renderer.timer = GpuTimer::new(&offscreen.device, &offscreen.queue);
+    let fps = options.fixed_fps.unwrap_or(HEADLESS_FPS);
+    offscreen.uniforms.delta = 1.0 / fps;
+
+    let mut frame_times = Vec::with_capacity(bench.frames as usize);
+    let mut gpu_times = Vec::new();
+    let start = Instant::now();
+    for frame in 0..bench.frames {
+        offscreen.uniforms.time = frame as f32 / fps * options.speed;
+        offscreen.uniforms.frame = frame;
+
+        let frame_start = Instant::now();
+        offscreen.draw();
+        if let Some(timer) = &mut offscreen.renderer.timer {
+            timer.submitted();
+        }
+        offscreen.device.poll(wgpu::Maintain::Wait);
+        frame_times.push(frame_start.elapsed().as_secs_f32() * 1000.0);
+
+        if let Some(elapsed) = offscreen
+            .renderer
+            .timer
+            .as_mut()
+            .and_then(GpuTimer::collect)
+        {
+            gpu_times.push(elapsed);
+        }
+    }
+    let total = start.elapsed().as_secs_f32();
+
+    println!(
+        "Rendered {} frames at {}x{} in {:.2} s ({:.1} FPS)",
+        bench.frames,
+        bench.width,
+        bench.height,
+        total,
+        bench.frames as f32 / total
+    );
+    println!("Frame time: {}", summary(&mut frame_times));
+    if gpu_times.is_empty() {
+        println!("GPU time: not available, the adapter doesn't support timestamp queries");
+    } else {
+        println!("GPU time: {}", summary(&mut gpu_times));
+    }
+    Ok(())
+}
+
+// Min, mean, max and tail percentiles of a set of millisecond timings
+fn summary(times: &mut [f32]) -> String {
+    times.sort_by(f32::total_cmp);
+    let mean = times.iter().sum::<f32>() / times.len() as f32;
+    format!(
+        "min {:.3} ms, mean {:.3} ms, max {:.3} ms, p95 {:.3} ms, p99 {:.3} ms",
+        times[0],
+        mean,
+        times[times.len() - 1],
+        percentile(times, 95.0),
+        percentile(times, 99.0)
+    )
+}
+
+// Nearest-rank percentile of sorted `times`
+fn percentile(times: &[f32], percent: f32) -> f32 {
+    let rank = (percent / 100.0 * times.len() as f32).ceil() as usize;
+    times[rank.clamp(1, times.len()) - 1]
+}
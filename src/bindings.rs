@@ -0,0 +1,257 @@
+use crate::channel;
+use crate::{Uniforms, COMPUTE_FORMAT};
+
+// Something the renderer can bind in group 0 for the shader pass. Shaders
+// declare the ones they read at fixed bindings, and reflection picks those out
+// so the layout and bind groups only hold what the shader uses.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Resource {
+    Uniforms,
+    PreviousFrame,
+    PreviousFrameSampler,
+    Channel(usize),
+    ChannelSampler,
+    ComputeOutput,
+    BlueNoise,
+}
+
+impl Resource {
+    // What's bound at `binding`, for a renderer with `channels` inputs
+    fn at(binding: u32, channels: usize, compute: bool) -> Option<Self> {
+        let resource = match binding {
+            0 => Resource::Uniforms,
+            1 => Resource::PreviousFrame,
+            2 => Resource::PreviousFrameSampler,
+            channel::SAMPLER_BINDING => Resource::ChannelSampler,
+            5 if compute => Resource::ComputeOutput,
+            9 => Resource::BlueNoise,
+            _ => {
+                let index = (0..channels).find(|&index| channel::binding(index) == binding)?;
+                Resource::Channel(index)
+            }
+        };
+        Some(resource)
+    }
+
+    pub fn binding(self) -> u32 {
+        match self {
+            Resource::Uniforms => 0,
+            Resource::PreviousFrame => 1,
+            Resource::PreviousFrameSampler => 2,
+            Resource::Channel(index) => channel::binding(index),
+            Resource::ChannelSampler => channel::SAMPLER_BINDING,
+            Resource::ComputeOutput => 5,
+            Resource::BlueNoise => 9,
+        }
+    }
+
+    pub fn layout_entry(self, visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+        let ty = match self {
+            Resource::Uniforms => wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<Uniforms>() as u64),
+            },
+            Resource::PreviousFrame | Resource::Channel(_) | Resource::BlueNoise => {
+                wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                }
+            }
+            Resource::PreviousFrameSampler | Resource::ChannelSampler => {
+                wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering)
+            }
+            Resource::ComputeOutput => wgpu::BindingType::StorageTexture {
+                access: wgpu::StorageTextureAccess::WriteOnly,
+                format: COMPUTE_FORMAT,
+                view_dimension: wgpu::TextureViewDimension::D2,
+            },
+        };
+        wgpu::BindGroupLayoutEntry {
+            binding: self.binding(),
+            visibility,
+            ty,
+            count: None,
+        }
+    }
+
+    fn describe(self) -> String {
+        match self {
+            Resource::Uniforms => "the uniforms".to_string(),
+            Resource::PreviousFrame => "the previous frame".to_string(),
+            Resource::PreviousFrameSampler => "the previous frame's sampler".to_string(),
+            Resource::Channel(index) => format!("channel{}", index),
+            Resource::ChannelSampler => "the channel sampler".to_string(),
+            Resource::ComputeOutput => "the compute output".to_string(),
+            Resource::BlueNoise => "the blue noise texture".to_string(),
+        }
+    }
+
+    // What the shader has to declare to read it
+    fn expected(self) -> &'static str {
+        match self {
+            Resource::Uniforms => "a uniform buffer",
+            Resource::PreviousFrame | Resource::Channel(_) | Resource::BlueNoise => {
+                "a texture_2d<f32>"
+            }
+            Resource::PreviousFrameSampler | Resource::ChannelSampler => "a sampler",
+            Resource::ComputeOutput => "a texture_storage_2d",
+        }
+    }
+
+    fn matches(self, space: naga::AddressSpace, ty: &naga::TypeInner) -> bool {
+        use naga::{ImageClass, ImageDimension, ScalarKind, TypeInner};
+        match self {
+            Resource::Uniforms => space == naga::AddressSpace::Uniform,
+            Resource::PreviousFrame | Resource::Channel(_) | Resource::BlueNoise => matches!(
+                ty,
+                TypeInner::Image {
+                    dim: ImageDimension::D2,
+                    arrayed: false,
+                    class: ImageClass::Sampled {
+                        kind: ScalarKind::Float,
+                        multi: false,
+                    },
+                }
+            ),
+            Resource::PreviousFrameSampler | Resource::ChannelSampler => {
+                matches!(ty, TypeInner::Sampler { comparison: false })
+            }
+            Resource::ComputeOutput => matches!(
+                ty,
+                TypeInner::Image {
+                    dim: ImageDimension::D2,
+                    arrayed: false,
+                    class: ImageClass::Storage { .. },
+                }
+            ),
+        }
+    }
+}
+
+// Everything a renderer with `channels` inputs can bind. Used as is for
+// sources that don't parse, so wgpu gets to report their errors.
+pub fn all(channels: usize, compute: bool) -> Vec<Resource> {
+    let mut resources = vec![
+        Resource::Uniforms,
+        Resource::PreviousFrame,
+        Resource::PreviousFrameSampler,
+        Resource::ChannelSampler,
+    ];
+    resources.extend((0..channels).map(Resource::Channel));
+    resources.push(Resource::BlueNoise);
+    if compute {
+        resources.push(Resource::ComputeOutput);
+    }
+    resources
+}
+
+// The resources `module` declares, in binding order, with an error for any
+// declaration the renderer has nothing to bind to. The uniforms are always
+// included: they may have been moved into push constants, and keeping them
+// in the layout means it doesn't change when that happens.
+pub fn reflect(
+    module: &naga::Module,
+    channels: usize,
+    compute: bool,
+) -> Result<Vec<Resource>, String> {
+    let mut resources = vec![Resource::Uniforms];
+    for (_, global) in module.global_variables.iter() {
+        let Some(binding) = &global.binding else {
+            continue;
+        };
+        let name = global.name.as_deref().unwrap_or("?");
+        if binding.group != 0 {
+            return Err(format!(
+                "'{}' is in bind group {}, but only group 0 is bound",
+                name, binding.group
+            ));
+        }
+        let Some(resource) = Resource::at(binding.binding, channels, compute) else {
+            if let Some(index) =
+                (0..channel::MAX_CHANNELS).find(|&index| channel::binding(index) == binding.binding)
+            {
+                return Err(format!(
+                    "'{}' at binding {} reads channel{}, which needs --channel{}",
+                    name, binding.binding, index, index
+                ));
+            }
+            return Err(format!(
+                "'{}' is declared at binding {}, which nothing is bound to",
+                name, binding.binding
+            ));
+        };
+        if !resource.matches(global.space, &module.types[global.ty].inner) {
+            return Err(format!(
+                "'{}' at binding {} should be {}, since that's where {} is bound",
+                name,
+                binding.binding,
+                resource.expected(),
+                resource.describe()
+            ));
+        }
+        if !resources.contains(&resource) {
+            resources.push(resource);
+        }
+    }
+    resources.sort_by_key(|resource| resource.binding());
+    Ok(resources)
+}
+
+// Whether `module` reads the `keys` uniform, from the uniform buffer or from
+// push constants. Fields are told apart by offset, since shaders may name them
+// as they like.
+pub fn reads_keys(module: &naga::Module) -> bool {
+    let keys_offset = std::mem::offset_of!(Uniforms, keys) as u32;
+    let uniforms: Vec<_> = module
+        .global_variables
+        .iter()
+        .filter(|(_, global)| match global.space {
+            naga::AddressSpace::Uniform => global
+                .binding
+                .as_ref()
+                .is_some_and(|binding| binding.group == 0 && binding.binding == 0),
+            naga::AddressSpace::PushConstant => true,
+            _ => false,
+        })
+        .filter_map(|(handle, global)| match &module.types[global.ty].inner {
+            naga::TypeInner::Struct { members, .. } => members
+                .iter()
+                .position(|member| member.offset == keys_offset)
+                .map(|index| (handle, index as u32)),
+            _ => None,
+        })
+        .collect();
+
+    let functions = module
+        .functions
+        .iter()
+        .map(|(_, function)| function)
+        .chain(module.entry_points.iter().map(|entry| &entry.function));
+    for function in functions {
+        // The global itself, or the struct loaded out of it
+        let global =
+            |expression: naga::Handle<naga::Expression>| match function.expressions[expression] {
+                naga::Expression::GlobalVariable(handle) => Some(handle),
+                naga::Expression::Load { pointer } => match function.expressions[pointer] {
+                    naga::Expression::GlobalVariable(handle) => Some(handle),
+                    _ => None,
+                },
+                _ => None,
+            };
+        let reads = function
+            .expressions
+            .iter()
+            .any(|(_, expression)| match *expression {
+                naga::Expression::AccessIndex { base, index } => {
+                    global(base).is_some_and(|handle| uniforms.contains(&(handle, index)))
+                }
+                _ => false,
+            });
+        if reads {
+            return true;
+        }
+    }
+    false
+}
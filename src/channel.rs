@@ -0,0 +1,83 @@
+use crate::texture::Texture;
+use std::path::PathBuf;
+
+// Shadertoy allows four inputs, iChannel0 to iChannel3
+pub const MAX_CHANNELS: usize = 4;
+
+// What a `--channelN` flag asks for
+#[derive(Clone, Debug)]
+pub enum ChannelSpec {
+    Image(PathBuf),
+    PreviousFrame,
+    Noise,
+}
+
+impl ChannelSpec {
+    // `prev` and `noise` are keywords, anything else is an image path
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "prev" => ChannelSpec::PreviousFrame,
+            "noise" => ChannelSpec::Noise,
+            _ => ChannelSpec::Image(PathBuf::from(value)),
+        }
+    }
+}
+
+// An input bound for the shader to sample as `channelN`
+pub enum Channel {
+    Texture(Texture),
+    // The renderer's previous frame, the same texture as `prev_frame`
+    PreviousFrame,
+}
+
+impl Channel {
+    pub fn load(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        spec: &ChannelSpec,
+        mipmaps: bool,
+    ) -> Result<Self, String> {
+        Ok(match spec {
+            ChannelSpec::Image(path) => {
+                Channel::Texture(Texture::load(device, queue, path, mipmaps)?)
+            }
+            ChannelSpec::PreviousFrame => Channel::PreviousFrame,
+            ChannelSpec::Noise => Channel::Texture(Texture::noise(device, queue)),
+        })
+    }
+
+    // Load every channel up to the highest one given. `channel0` is always
+    // bound so shaders written against it work without any flags, and gaps
+    // below the highest channel get a white placeholder.
+    pub fn load_all(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        specs: &[Option<ChannelSpec>; MAX_CHANNELS],
+        mipmaps: bool,
+    ) -> Result<Vec<Self>, String> {
+        let count = specs
+            .iter()
+            .rposition(Option::is_some)
+            .map_or(1, |last| last + 1);
+        specs[..count]
+            .iter()
+            .map(|spec| match spec {
+                Some(spec) => Channel::load(device, queue, spec, mipmaps),
+                None => Ok(Channel::Texture(Texture::placeholder(device, queue))),
+            })
+            .collect()
+    }
+}
+
+// Where `channelN` is bound. `channel0` predates the others and keeps binding
+// 3, with the sampler every channel shares at 4, and the rest come after the
+// compute output at 5.
+pub fn binding(index: usize) -> u32 {
+    match index {
+        0 => 3,
+        _ => 5 + index as u32,
+    }
+}
+
+// Binding of the sampler shared by all channels
+pub const SAMPLER_BINDING: u32 = 4;
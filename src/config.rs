@@ -0,0 +1,147 @@
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Window size for a config that only sets one of `width` and `height`
+const DEFAULT_SIZE: (u32, u32) = (1280, 720);
+
+// Settings read by `--config` from a TOML file. Every key is optional, and
+// each one only applies when the flag it stands in for isn't given.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // SHADER, relative to the config file
+    pub shader_path: Option<PathBuf>,
+    // --fullscreen
+    pub fullscreen: bool,
+    // --windowed WIDTHxHEIGHT
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    // --present-mode
+    pub present_mode: Option<String>,
+    // --msaa
+    pub msaa: Option<u32>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read config '{}': {}", path.display(), err))?;
+        let mut config: Self = toml::from_str(&text)
+            .map_err(|err| format!("invalid config '{}': {}", path.display(), err))?;
+        if let (Some(shader_path), Some(dir)) = (&config.shader_path, path.parent()) {
+            config.shader_path = Some(dir.join(shader_path));
+        }
+        Ok(config)
+    }
+
+    // The size `width` and `height` ask for, if either is set
+    pub fn windowed(&self) -> Option<(u32, u32)> {
+        match (self.width, self.height) {
+            (None, None) => None,
+            (width, height) => Some((
+                width.unwrap_or(DEFAULT_SIZE.0),
+                height.unwrap_or(DEFAULT_SIZE.1),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Options;
+
+    const SAMPLE: &str = r#"
+shader_path = "shaders/waves.wgsl"
+width = 800
+height = 600
+present_mode = "mailbox"
+msaa = 4
+"#;
+
+    // `SAMPLE` written to a directory of its own, named after the test
+    fn write_sample(test: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("shader-{}-{}", test, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("shader.toml");
+        fs::write(&path, SAMPLE).unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn parses_sample() {
+        let config: Config = toml::from_str(SAMPLE).unwrap();
+        assert_eq!(
+            config.shader_path,
+            Some(PathBuf::from("shaders/waves.wgsl"))
+        );
+        assert!(!config.fullscreen);
+        assert_eq!(config.windowed(), Some((800, 600)));
+        assert_eq!(config.present_mode.as_deref(), Some("mailbox"));
+        assert_eq!(config.msaa, Some(4));
+    }
+
+    #[test]
+    fn missing_dimension_defaults() {
+        let config: Config = toml::from_str("width = 800").unwrap();
+        assert_eq!(config.windowed(), Some((800, DEFAULT_SIZE.1)));
+        assert_eq!(Config::default().windowed(), None);
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let err = toml::from_str::<Config>("fulscreen = true")
+            .err()
+            .expect("a typo'd key is an error");
+        assert!(err.to_string().contains("fulscreen"), "{}", err);
+    }
+
+    #[test]
+    fn shader_path_is_relative_to_config() {
+        let (dir, path) = write_sample("config-load");
+        let config = Config::load(&path);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            config.unwrap().shader_path,
+            Some(dir.join("shaders/waves.wgsl"))
+        );
+    }
+
+    #[test]
+    fn flags_override_config() {
+        let (dir, path) = write_sample("config-flags");
+        let config = path.to_str().unwrap();
+        let from_config = Options::parse(["--config", config].map(String::from));
+        let overridden = Options::parse(
+            [
+                "--config",
+                config,
+                "--windowed",
+                "1024x768",
+                "--present-mode",
+                "immediate",
+                "--msaa",
+                "2",
+                "other.wgsl",
+            ]
+            .map(String::from),
+        );
+        fs::remove_dir_all(&dir).unwrap();
+
+        let from_config = from_config.unwrap();
+        assert_eq!(
+            from_config.shader_path,
+            Some(dir.join("shaders/waves.wgsl"))
+        );
+        assert_eq!(from_config.windowed, Some((800, 600)));
+        assert_eq!(from_config.present_mode, Some(wgpu::PresentMode::Mailbox));
+        assert_eq!(from_config.msaa, 4);
+
+        let overridden = overridden.unwrap();
+        assert_eq!(overridden.shader_path, Some(PathBuf::from("other.wgsl")));
+        assert_eq!(overridden.windowed, Some((1024, 768)));
+        assert_eq!(overridden.present_mode, Some(wgpu::PresentMode::Immediate));
+        assert_eq!(overridden.msaa, 2);
+    }
+}
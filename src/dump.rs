@@ -0,0 +1,202 @@
+use crate::options::FrameDump;
+use crate::{swaps_red_blue, Renderer};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+
+// Most frames read back at once. Frames that come due while this many are
+// still in flight are skipped rather than stalling presentation on them.
+const MAX_PENDING: usize = 3;
+
+// A frame on its way back from the GPU
+struct Readback {
+    buffer: wgpu::Buffer,
+    frame: u32,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    // Receives the result of mapping the buffer, once the copy has been
+    // submitted and the map requested
+    mapped: Option<mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>>,
+}
+
+// Saves every `--every`th frame of an interactive run into `--frame-dump-dir`.
+// Each one is blitted into a texture of its own so the overlay and panel stay
+// out of it, copied into a buffer and mapped asynchronously, and the PNGs are
+// encoded on a separate thread, so the main loop only records the copies.
+pub struct FrameDumper {
+    dir: PathBuf,
+    every: u32,
+    swap_red_blue: bool,
+    pending: VecDeque<Readback>,
+    // Both are dropped by `finish`, to let the writer thread run out
+    writer_tx: Option<mpsc::Sender<(PathBuf, image::RgbaImage)>>,
+    writer: Option<thread::JoinHandle<()>>,
+}
+
+impl FrameDumper {
+    // Frames are read back in the renderer's output `format`
+    pub fn new(settings: &FrameDump, format: wgpu::TextureFormat) -> Result<Self, String> {
+        let swap_red_blue = swaps_red_blue(format)?;
+        fs::create_dir_all(&settings.dir)
+            .map_err(|err| format!("failed to create '{}': {}", settings.dir.display(), err))?;
+
+        let (writer_tx, writer_rx) = mpsc::channel::<(PathBuf, image::RgbaImage)>();
+        let writer = thread::spawn(move || {
+            for (path, image) in writer_rx {
+                if let Err(err) = image.save(&path) {
+                    log::warn!("Failed to save frame to '{}': {}", path.display(), err);
+                }
+            }
+        });
+
+        Ok(Self {
+            dir: settings.dir.clone(),
+            every: settings.every,
+            swap_red_blue,
+            pending: VecDeque::with_capacity(MAX_PENDING),
+            writer_tx: Some(writer_tx),
+            writer: Some(writer),
+        })
+    }
+
+    // Record a copy of the frame `renderer` just drew, if it's one to save
+    pub fn capture(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        renderer: &Renderer,
+        frame: u32,
+        width: u32,
+        height: u32,
+    ) {
+        if !frame.is_multiple_of(self.every) {
+            return;
+        }
+        if self.pending.len() == MAX_PENDING {
+            log::warn!(
+                "Skipped saving frame {}, earlier ones are still being read back",
+                frame
+            );
+            return;
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Frame Dump Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: renderer.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        renderer.blit(
+            encoder,
+            &texture.create_view(&wgpu::TextureViewDescriptor::default()),
+        );
+
+        // Rows in a texture-to-buffer copy must be padded to a multiple of 256 bytes
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = (width * 4).div_ceil(align) * align;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Frame Dump Buffer"),
+            size: (padded_bytes_per_row * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+
+        self.pending.push_back(Readback {
+            buffer,
+            frame,
+            width,
+            height,
+            padded_bytes_per_row,
+            mapped: None,
+        });
+    }
+
+    // Start mapping the frames captured in the work just submitted
+    pub fn submitted(&mut self) {
+        for readback in self.pending.iter_mut() {
+            if readback.mapped.is_some() {
+                continue;
+            }
+            let (map_tx, map_rx) = mpsc::channel();
+            readback
+                .buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    let _ = map_tx.send(result);
+                });
+            readback.mapped = Some(map_rx);
+        }
+    }
+
+    // Hand the frames that finished reading back to the writer, in order. Map
+    // callbacks only run while the device is polled.
+    pub fn collect(&mut self) {
+        while let Some(readback) = self.pending.front() {
+            let Some(Ok(result)) = readback.mapped.as_ref().map(mpsc::Receiver::try_recv) else {
+                break;
+            };
+            let readback = self.pending.pop_front().unwrap();
+            if let Err(err) = result {
+                log::warn!("Failed to read back frame {}: {}", readback.frame, err);
+                continue;
+            }
+
+            let unpadded_bytes_per_row = (readback.width * 4) as usize;
+            let mut pixels = Vec::with_capacity(unpadded_bytes_per_row * readback.height as usize);
+            for row in readback
+                .buffer
+                .slice(..)
+                .get_mapped_range()
+                .chunks(readback.padded_bytes_per_row as usize)
+            {
+                pixels.extend_from_slice(&row[..unpadded_bytes_per_row]);
+            }
+            readback.buffer.unmap();
+            if self.swap_red_blue {
+                for pixel in pixels.chunks_mut(4) {
+                    pixel.swap(0, 2);
+                }
+            }
+
+            let path = self.dir.join(format!("frame_{:05}.png", readback.frame));
+            let image = image::RgbaImage::from_raw(readback.width, readback.height, pixels)
+                .expect("readback matches the frame size");
+            if let Some(writer_tx) = &self.writer_tx {
+                let _ = writer_tx.send((path, image));
+            }
+        }
+    }
+
+    // Wait for the frames still in flight and for every PNG to be written
+    pub fn finish(&mut self, device: &wgpu::Device) {
+        device.poll(wgpu::Maintain::Wait);
+        self.collect();
+        self.writer_tx = None;
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::fs;
+use std::path::PathBuf;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::window::{Window, WindowBuilder};
+
+// Where the window was and how big it was when the app last exited, in
+// physical pixels. Stored in the platform config directory as a single
+// `WIDTHxHEIGHT+X+Y` line, or just `WIDTHxHEIGHT` where windows can't be
+// positioned, such as on Wayland.
+#[derive(Clone, Copy)]
+pub struct WindowGeometry {
+    size: PhysicalSize<u32>,
+    position: Option<PhysicalPosition<i32>>,
+}
+
+impl WindowGeometry {
+    // `None` on the first run, or if the file can't be read
+    pub fn load() -> Option<Self> {
+        let text = fs::read_to_string(path()?).ok()?;
+        parse(text.trim())
+    }
+
+    pub fn restore(&self, builder: WindowBuilder) -> WindowBuilder {
+        let builder = builder.with_inner_size(self.size);
+        match self.position {
+            Some(position) => builder.with_position(position),
+            None => builder,
+        }
+    }
+
+    pub fn save(&self) {
+        let Some(path) = path() else {
+            return;
+        };
+        let mut text = format!("{}x{}", self.size.width, self.size.height);
+        if let Some(position) = self.position {
+            text.push_str(&format!("{:+}{:+}", position.x, position.y));
+        }
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&path, text + "\n"));
+        if let Err(err) = result {
+            log::warn!(
+                "Failed to save the window geometry to '{}': {}",
+                path.display(),
+                err
+            );
+        }
+    }
+
+    // The current geometry of `window`, or `None` while it's fullscreen or
+    // minimized, since the windowed geometry is what should come back
+    pub fn of(window: &Window) -> Option<Self> {
+        let size = window.inner_size();
+        if window.fullscreen().is_some() || size.width == 0 || size.height == 0 {
+            return None;
+        }
+        Some(Self {
+            size,
+            position: window.outer_position().ok(),
+        })
+    }
+}
+
+fn path() -> Option<PathBuf> {
+    let dirs = directories::ProjectDirs::from("", "", "shader")?;
+    Some(dirs.config_dir().join("window"))
+}
+
+fn parse(text: &str) -> Option<WindowGeometry> {
+    // Split off the signed offsets, if any
+    let split = text.find(['+', '-']).unwrap_or(text.len());
+    let (size, offsets) = text.split_at(split);
+
+    let (width, height) = size.split_once('x')?;
+    let size = PhysicalSize::new(width.parse().ok()?, height.parse().ok()?);
+    if size.width == 0 || size.height == 0 {
+        return None;
+    }
+
+    let position = if offsets.is_empty() {
+        None
+    } else {
+        let split = offsets[1..].find(['+', '-'])? + 1;
+        let (x, y) = offsets.split_at(split);
+        Some(PhysicalPosition::new(x.parse().ok()?, y.parse().ok()?))
+    };
+    Some(WindowGeometry { size, position })
+}
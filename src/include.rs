@@ -0,0 +1,70 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Read a shader file and splice in every `#include "file"` line, recursively.
+// Included paths are relative to the file that includes them. Errors name the
+// file and line of each include leading to the problem. Also returns every
+// file read, `path` first, so they can be watched for changes.
+pub fn expand(path: &Path) -> Result<(String, Vec<PathBuf>), String> {
+    let code = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let mut output = String::new();
+    let mut files = vec![path.to_path_buf()];
+    expand_into(path, &code, &mut Vec::new(), &mut files, &mut output)?;
+    Ok((output, files))
+}
+
+fn expand_into(
+    path: &Path,
+    code: &str,
+    stack: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+    output: &mut String,
+) -> Result<(), String> {
+    stack.push(canonical(path));
+
+    for (index, line) in code.lines().enumerate() {
+        let Some(directive) = line.trim().strip_prefix("#include") else {
+            output.push_str(line);
+            output.push('\n');
+            continue;
+        };
+
+        let location = format!("{}:{}", path.display(), index + 1);
+        let name = directive
+            .trim()
+            .strip_prefix('"')
+            .and_then(|rest| rest.strip_suffix('"'))
+            .ok_or_else(|| format!("{}: expected #include \"FILE\"", location))?;
+
+        let include = path.parent().unwrap_or(Path::new("")).join(name);
+        if stack.contains(&canonical(&include)) {
+            return Err(format!(
+                "{}: include cycle through '{}'",
+                location,
+                include.display()
+            ));
+        }
+
+        let included = fs::read_to_string(&include).map_err(|err| {
+            format!(
+                "{}: failed to read '{}': {}",
+                location,
+                include.display(),
+                err
+            )
+        })?;
+        if !files.contains(&include) {
+            files.push(include.clone());
+        }
+        expand_into(&include, &included, stack, files, output)
+            .map_err(|err| format!("{}: in '{}'\n{}", location, include.display(), err))?;
+    }
+
+    stack.pop();
+    Ok(())
+}
+
+// Resolve `..` and symlinks so cycles are caught however a file is reached
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
@@ -0,0 +1,4553 @@
+// The renderer behind the `shader` command. `run` runs the command itself, and
+// `ShaderApp` draws a shader into a window owned by code embedding it.
+
+#[cfg(not(target_arch = "wasm32"))]
+mod app;
+#[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+mod audio;
+#[cfg(not(target_arch = "wasm32"))]
+mod bench;
+mod bindings;
+mod channel;
+mod config;
+#[cfg(not(target_arch = "wasm32"))]
+mod dump;
+#[cfg(not(target_arch = "wasm32"))]
+mod geometry;
+mod include;
+mod options;
+mod overlay;
+mod palette;
+mod panel;
+mod presets;
+#[cfg(not(target_arch = "wasm32"))]
+mod record;
+mod shadertoy;
+mod texture;
+mod timer;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use app::{RenderError, ShaderApp};
+pub use options::{Options, UsageError};
+
+use bindings::Resource;
+use channel::Channel;
+#[cfg(not(target_arch = "wasm32"))]
+use geometry::WindowGeometry;
+#[cfg(not(target_arch = "wasm32"))]
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+#[cfg(not(target_arch = "wasm32"))]
+use options::Headless;
+use options::{Colorspace, FullscreenGeometry, LimitsPreset, PostEffect, Tonemap};
+use overlay::Overlay;
+use palette::Palette;
+use panel::Panel;
+use presets::PRESETS;
+use std::borrow::Cow;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::{Arc, Mutex};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+use timer::GpuTimer;
+use wgpu::util::DeviceExt;
+// `std::time::Instant` panics in the browser
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+#[cfg(not(target_arch = "wasm32"))]
+use winit::monitor::MonitorHandle;
+#[cfg(not(target_arch = "wasm32"))]
+use winit::window::Fullscreen;
+use winit::{
+    dpi::{LogicalSize, PhysicalSize},
+    event::{
+        ElementState, Event, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
+        VirtualKeyCode, WindowEvent,
+    },
+    event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
+    window::{Window, WindowBuilder},
+};
+
+// Vertex shader to transform vertices. `vs_main` covers the screen with one
+// triangle that overhangs it, `vs_quad` with two that stay within clip space
+// for drivers that mishandle the overhang, see --fullscreen-geometry.
+const VERTEX_SHADER: &str = r#"
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    var pos = array<vec2<f32>, 3>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(3.0, -1.0),
+        vec2<f32>(-1.0, 3.0)
+    );
+    return vec4<f32>(pos[vertex_index], 0.0, 1.0);
+}
+
+@vertex
+fn vs_quad(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    var pos = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0)
+    );
+    return vec4<f32>(pos[vertex_index], 0.0, 1.0);
+}
+"#;
+
+// The vertex entry point and vertex count that cover the screen with
+// `geometry`, in both `VERTEX_SHADER` and `BLIT_SHADER`
+fn fullscreen_vertices(geometry: FullscreenGeometry) -> (&'static str, u32) {
+    match geometry {
+        FullscreenGeometry::Triangle => ("vs_main", 3),
+        FullscreenGeometry::Quad => ("vs_quad", 6),
+    }
+}
+
+// Fragment shader for psychedelic effects with added grain
+const FRAGMENT_SHADER: &str = r#"
+// `mouse` follows Shadertoy's iMouse: xy is the cursor position and zw the
+// last click position, both in pixels with the origin at the top-left. zw is
+// negated while no button is held.
+// `frame` counts rendered frames and `delta` is the seconds since the last one.
+// `dpi` is the number of rendered pixels per logical pixel, which takes the
+// display's scale factor and `--scale` into account.
+// `frequencies` holds the horizontal, vertical, ring and spiral wave
+// frequencies, which the Tab panel edits along with the grain and pulse speed.
+// `audio` holds the bass, mid and treble levels and the overall volume of the
+// default audio input when built with the `audio` feature, and zeros
+// otherwise.
+// `seed` shifts the noise to a different but repeatable grain pattern.
+// `grain` is 0 for grain from the value noise below and 1 for grain from the
+// tiling blue noise texture at binding 9, see --grain.
+// `center` is where the waves and spiral are centered, as a fraction of the
+// resolution with the origin at the top-left like `mouse`.
+// `exposure` is how many stops the final pass brightens the frame by before
+// tone mapping it, see --exposure and --tonemap.
+// `resolution_px` is `resolution` as integers, for exact pixel math.
+// `keys` has a bit set for each of W, A, S, D, Up, Left, Down and Right that's
+// held down, from bit 0 in that order, for shaders that respond to them. While
+// a shader reads it, those keys stop doing what they do otherwise.
+struct Uniforms {
+    time: f32,
+    delta: f32,
+    resolution: vec2<f32>,
+    mouse: vec4<f32>,
+    frame: u32,
+    grain_intensity: f32,
+    pulse_speed: f32,
+    dpi: f32,
+    frequencies: vec4<f32>,
+    audio: vec4<f32>,
+    seed: f32,
+    grain: u32,
+    center: vec2<f32>,
+    exposure: f32,
+    keys: u32,
+    resolution_px: vec2<u32>,
+    palette_size: u32,
+    zoom: f32,
+    offset: vec2<f32>,
+    palette: array<vec4<f32>, 8>,
+};
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+@group(0) @binding(9)
+var blue_noise: texture_2d<f32>;
+
+// Hash function for pseudo-random numbers
+fn hash(p: vec2<f32>) -> f32 {
+    let seeded = p + vec2<f32>(uniforms.seed, uniforms.seed * 1.618);
+    var h = dot(seeded, vec2<f32>(127.1, 311.7));
+    return fract(sin(h) * 43758.5453123);
+}
+
+// Map a pixel position to aspect-correct coordinates: the origin is at the
+// center of the screen, y points up and the shorter side spans -0.5 to 0.5,
+// so one unit is the same length on both axes whatever the window shape
+fn to_uv(pixel: vec2<f32>) -> vec2<f32> {
+    let size = uniforms.resolution;
+    let uv = (pixel - 0.5 * size) / min(size.x, size.y);
+    return vec2<f32>(uv.x, -uv.y);
+}
+
+// Where a pixel lands in the pattern, after zooming by `zoom` and panning the
+// view to `offset`, both in `to_uv` units
+fn to_pattern(pixel: vec2<f32>) -> vec2<f32> {
+    return to_uv(pixel) / uniforms.zoom + uniforms.offset;
+}
+
+// Smooth lookup into the palette, which `t` runs through once per unit,
+// easing from each color into the next and from the last back to the first
+fn palette(t: f32) -> vec3<f32> {
+    let x = fract(t) * f32(uniforms.palette_size);
+    let i = min(u32(x), uniforms.palette_size - 1u);
+    let next = (i + 1u) % uniforms.palette_size;
+    return mix(uniforms.palette[i].rgb, uniforms.palette[next].rgb, smoothstep(0.0, 1.0, fract(x)));
+}
+
+// Noise function
+fn noise(p: vec2<f32>) -> f32 {
+    let i = floor(p);
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+    
+    let a = hash(i);
+    let b = hash(i + vec2<f32>(1.0, 0.0));
+    let c = hash(i + vec2<f32>(0.0, 1.0));
+    let d = hash(i + vec2<f32>(1.0, 1.0));
+    
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
+}
+
+// Film grain between -1 and 1 at a position in logical pixels, so it looks the
+// same on any display
+fn film_grain(pixel: vec2<f32>, time: f32) -> f32 {
+    if (uniforms.grain == 1u) {
+        // Jump to another part of the texture 24 times a second, like film
+        let size = vec2<f32>(textureDimensions(blue_noise));
+        let step = floor(time * 24.0);
+        let offset = vec2<f32>(hash(vec2<f32>(step, 0.0)), hash(vec2<f32>(0.0, step))) * size;
+        let texel = vec2<i32>((pixel + offset) % size);
+        return textureLoad(blue_noise, texel, 0).r * 2.0 - 1.0;
+    }
+
+    // Animated with time
+    let grain_speed = 5.0; // How quickly the grain pattern changes
+    let grain_pos = pixel + time * grain_speed;
+    return noise(grain_pos * 20.0) * 2.0 - 1.0;
+}
+
+// The pattern at a pixel position, shared with the compute version
+fn psychedelic(pixel: vec2<f32>) -> vec4<f32> {
+    let time = uniforms.time;
+    let position = to_pattern(pixel);
+    
+    // Circular waves around the center
+    let center = to_uv(uniforms.center * uniforms.resolution);
+    let dist = distance(position, center);
+    
+    // Psychedelic color mixing
+    let frequencies = uniforms.frequencies;
+    let r = sin(position.x * frequencies.x + time * 0.1) * 0.5 + 0.5;
+    let g = cos(position.y * frequencies.y - time * 0.2) * 0.5 + 0.5;
+    let b = sin(dist * frequencies.z - time * 0.3) * 0.5 + 0.5;
+    
+    // Warping effect
+    let warp = sin(position.x * 5.0 + time) * cos(position.y * 5.0 + time * 0.2) * 0.1;
+    let warp_pos = position + vec2<f32>(warp, warp);
+    
+    // Spiral patterns
+    let angle = atan2(warp_pos.y - center.y, warp_pos.x - center.x);
+    let spiral = sin(dist * frequencies.w + angle * 5.0 + time * 0.2) * 0.5 + 0.5;
+    
+    // Grain effect - high frequency noise
+    let grain_intensity = uniforms.grain_intensity;
+    let grain = film_grain(pixel / uniforms.dpi, time);
+    
+    // Final color mixing
+    var color = vec3<f32>(
+        r * spiral + 0.2 * sin(time * 0.2 + position.x * 5.0),
+        g * spiral + 0.2 * cos(time * 0.3 + position.y * 3.0),
+        b * spiral + 0.2 * sin(time * 0.1 + dist * 10.0)
+    );
+    
+    // Recolor by brightness, drifting through the palette over time
+    if (uniforms.palette_size > 0u) {
+        color = palette(dot(color, vec3<f32>(1.0 / 3.0)) + time * 0.05);
+    }
+
+    // Apply grain to color
+    let color_with_grain = color + vec3<f32>(grain * grain_intensity);
+    
+    // Pulsing effect, kicked up by the bass
+    let pulse = sin(time * uniforms.pulse_speed) * 0.1 + 0.9 + min(uniforms.audio.x * 2.0, 0.5);
+    
+    return vec4<f32>(color_with_grain * pulse, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    return psychedelic(pos.xy);
+}
+"#;
+
+// Appended to `FRAGMENT_SHADER` to make the built-in `--compute` shader. Each
+// invocation writes one pixel of the output, which is bound at binding 5 in
+// compute mode; invocations past the edge of the texture do nothing.
+#[cfg(not(target_arch = "wasm32"))]
+const COMPUTE_ENTRY: &str = r#"
+@group(0) @binding(5)
+var output: texture_storage_2d<rgba8unorm, write>;
+
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(output);
+    if (id.x >= size.x || id.y >= size.y) {
+        return;
+    }
+    textureStore(output, vec2<i32>(id.xy), psychedelic(vec2<f32>(id.xy) + 0.5));
+}
+"#;
+
+// Must match `@workgroup_size` in compute shaders
+const WORKGROUP_SIZE: u32 = 8;
+
+// Format of the storage texture compute shaders write into. sRGB formats
+// can't be used for storage, so values are written as they're meant to be
+// displayed and the blit encodes them like a fragment shader's output.
+const COMPUTE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+// Format of the shader pass's depth buffer with --depth
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+// Mirrors the WGSL `Uniforms` struct: `time` and `delta` fill the first 8
+// bytes so `resolution` lands on its vec2 alignment, and `mouse` then starts on
+// the 16-byte boundary a vec4 requires. `dpi` happens to fill the rest of the
+// row, so `frequencies` lands on a 16-byte boundary too. `keys` after
+// `exposure` puts `resolution_px` on its vec2 alignment. `palette_size` and
+// `zoom` then put `offset` on its own, and the `palette` array after it starts
+// on the 16-byte boundary its vec4 elements need and ends the struct on one as
+// WGSL requires.
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    time: f32,
+    delta: f32,
+    resolution: [f32; 2],
+    mouse: [f32; 4],
+    frame: u32,
+    grain_intensity: f32,
+    pulse_speed: f32,
+    dpi: f32,
+    frequencies: [f32; 4],
+    audio: [f32; 4],
+    seed: f32,
+    grain: u32,
+    center: [f32; 2],
+    exposure: f32,
+    // Bits of the `KEYS` held down
+    keys: u32,
+    resolution_px: [u32; 2],
+    // No palette when 0, see `set_palette`
+    palette_size: u32,
+    zoom: f32,
+    offset: [f32; 2],
+    palette: [[f32; 4]; palette::MAX_COLORS],
+}
+
+// The layout above comes to the 256 bytes the WGSL struct takes. A field added
+// without fixing up the padding fails the build here instead of misreading
+// every field after it in the shader.
+const _: () = assert!(std::mem::size_of::<Uniforms>() == 256);
+
+impl Uniforms {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            time: 0.0,
+            delta: 0.0,
+            resolution: [width as f32, height as f32],
+            // Start with the cursor in the middle, where the pattern is
+            mouse: [width as f32 / 2.0, height as f32 / 2.0, 0.0, 0.0],
+            frame: 0,
+            grain_intensity: DEFAULT_GRAIN_INTENSITY,
+            pulse_speed: DEFAULT_PULSE_SPEED,
+            dpi: 1.0,
+            frequencies: DEFAULT_FREQUENCIES,
+            audio: [0.0; 4],
+            seed: 0.0,
+            grain: options::Grain::Value as u32,
+            center: [0.5, 0.5],
+            exposure: 0.0,
+            keys: 0,
+            resolution_px: [width, height],
+            palette_size: 0,
+            zoom: 1.0,
+            offset: [0.0, 0.0],
+            palette: [[0.0; 4]; palette::MAX_COLORS],
+        }
+    }
+
+    // `new` with the seed, grain, exposure and palette `options` ask for
+    fn configured(width: u32, height: u32, options: &Options) -> Self {
+        let mut uniforms = Self::new(width, height);
+        uniforms.seed = options.seed as f32;
+        uniforms.grain = options.grain as u32;
+        uniforms.exposure = options.exposure;
+        uniforms.set_palette(options.palette.as_ref());
+        uniforms
+    }
+
+    // Recolor the built-in shader with `palette`, or go back to its own colors
+    fn set_palette(&mut self, palette: Option<&Palette>) {
+        (self.palette_size, self.palette) =
+            palette.map_or((0, [[0.0; 4]; palette::MAX_COLORS]), Palette::entries);
+    }
+
+    // Set both the float and the integer resolution. The cursor and click
+    // positions in `mouse` are in pixels, so they scale along with it, and
+    // the click keeps the sign that says whether the button is held.
+    fn set_resolution(&mut self, width: u32, height: u32) {
+        let resolution = [width as f32, height as f32];
+        for (axis, &size) in resolution.iter().enumerate() {
+            if self.resolution[axis] > 0.0 {
+                let scale = size / self.resolution[axis];
+                self.mouse[axis] *= scale;
+                self.mouse[axis + 2] *= scale;
+            }
+        }
+        self.resolution = resolution;
+        self.resolution_px = [width, height];
+    }
+
+    // Move the pattern center, keeping it on screen
+    fn set_center(&mut self, center: [f32; 2]) {
+        self.center = center.map(|value| value.clamp(0.0, 1.0));
+    }
+
+    fn center_on_cursor(&mut self) {
+        self.set_center([
+            self.mouse[0] / self.resolution[0],
+            self.mouse[1] / self.resolution[1],
+        ]);
+    }
+
+    // Zoom the view by `factor`, keeping the part of the pattern under
+    // `pixel` where it is
+    fn zoom_at(&mut self, factor: f32, pixel: [f32; 2]) {
+        let uv = self.uv(pixel);
+        let zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        for (offset, uv) in self.offset.iter_mut().zip(uv) {
+            *offset += uv / self.zoom - uv / zoom;
+        }
+        self.zoom = zoom;
+    }
+
+    // Move the view so the pattern follows the cursor from `from` to `to`
+    fn pan(&mut self, from: [f32; 2], to: [f32; 2]) {
+        let (from, to) = (self.uv(from), self.uv(to));
+        for ((offset, from), to) in self.offset.iter_mut().zip(from).zip(to) {
+            *offset -= (to - from) / self.zoom;
+        }
+    }
+
+    fn reset_view(&mut self) {
+        self.zoom = 1.0;
+        self.offset = [0.0, 0.0];
+    }
+
+    // The shader's `to_uv`: centered, y up, the shorter side spanning one unit
+    fn uv(&self, pixel: [f32; 2]) -> [f32; 2] {
+        let [width, height] = self.resolution;
+        let scale = width.min(height);
+        [
+            (pixel[0] - 0.5 * width) / scale,
+            (0.5 * height - pixel[1]) / scale,
+        ]
+    }
+
+    // Set or clear the `keys` bit for a press or release of one of `KEYS`
+    fn track_key(&mut self, input: &KeyboardInput) {
+        let Some(bit) = input
+            .virtual_keycode
+            .and_then(|key| KEYS.iter().position(|&k| k == key))
+        else {
+            return;
+        };
+        match input.state {
+            ElementState::Pressed => self.keys |= 1 << bit,
+            ElementState::Released => self.keys &= !(1 << bit),
+        }
+    }
+
+    // Every field as a `name=value` line, vectors as comma-separated components
+    fn describe(&self) -> String {
+        let join = |values: &[f32]| {
+            values
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        [
+            format!("time={}", self.time),
+            format!("delta={}", self.delta),
+            format!("resolution={}", join(&self.resolution)),
+            format!(
+                "resolution_px={},{}",
+                self.resolution_px[0], self.resolution_px[1]
+            ),
+            format!("mouse={}", join(&self.mouse)),
+            format!("frame={}", self.frame),
+            format!("grain_intensity={}", self.grain_intensity),
+            format!("pulse_speed={}", self.pulse_speed),
+            format!("dpi={}", self.dpi),
+            format!("frequencies={}", join(&self.frequencies)),
+            format!("audio={}", join(&self.audio)),
+            format!("seed={}", self.seed),
+            format!("grain={}", self.grain),
+            format!("center={}", join(&self.center)),
+            format!("exposure={}", self.exposure),
+            format!("keys={:#010b}", self.keys),
+            format!("palette_size={}", self.palette_size),
+            format!("zoom={}", self.zoom),
+            format!("offset={}", join(&self.offset)),
+            format!("palette={}", join(self.palette.as_flattened())),
+        ]
+        .join("\n")
+    }
+}
+
+// Keys the `keys` uniform tracks, by bit
+const KEYS: [VirtualKeyCode; 8] = [
+    VirtualKeyCode::W,
+    VirtualKeyCode::A,
+    VirtualKeyCode::S,
+    VirtualKeyCode::D,
+    VirtualKeyCode::Up,
+    VirtualKeyCode::Left,
+    VirtualKeyCode::Down,
+    VirtualKeyCode::Right,
+];
+
+// Film grain strength at startup and how much each G press changes it
+const DEFAULT_GRAIN_INTENSITY: f32 = 0.05;
+const GRAIN_STEP: f32 = 0.01;
+
+// Starting values for the parameters only the Tab panel changes
+const DEFAULT_PULSE_SPEED: f32 = 0.2;
+const DEFAULT_FREQUENCIES: [f32; 4] = [10.0, 8.0, 15.0, 20.0];
+
+// Smallest step and largest magnitude for the animation speed keys
+const TIME_SCALE_STEP: f32 = 0.25;
+const MAX_TIME_SCALE: f32 = 8.0;
+
+// How far . steps the paused animation without --step or --fixed-fps
+const DEFAULT_STEP: f32 = 1.0 / 60.0;
+
+// While paused, how often the loop wakes to check for shader changes and
+// device loss between redraws
+#[cfg(not(target_arch = "wasm32"))]
+const PAUSED_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+// How many stops each E or Shift+E press changes the exposure by
+const EXPOSURE_STEP: f32 = 0.25;
+
+// Range of the mouse wheel zoom, and how much each line of scrolling zooms by
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 256.0;
+const ZOOM_STEP: f32 = 1.1;
+
+// Pixels of touchpad scrolling that count as one line of a mouse wheel
+const PIXELS_PER_LINE: f32 = 40.0;
+
+// How far Left and Right scrub the animation time, and Alt+Left and Alt+Right
+const SCRUB_STEP: f32 = 0.1;
+const SCRUB_JUMP: f32 = 1.0;
+
+// How far each Shift+WASD or Shift+arrow press moves the pattern center, as a
+// fraction of the resolution
+const CENTER_STEP: f32 = 0.01;
+
+// Animation clock that integrates real frame deltas rather than reading a
+// start instant, so pausing and changing speed never make the time jump
+struct Clock {
+    time: f32,
+    scale: f32,
+    paused: bool,
+}
+
+impl Clock {
+    fn new() -> Self {
+        Self {
+            time: 0.0,
+            scale: 1.0,
+            paused: false,
+        }
+    }
+
+    // Advance by `delta` real seconds and return the new animation time
+    fn advance(&mut self, delta: f32) -> f32 {
+        if !self.paused {
+            self.time += delta * self.scale;
+        }
+        self.time
+    }
+
+    // Advance a paused clock by `delta` real seconds, to step through frames
+    fn step(&mut self, delta: f32) -> f32 {
+        self.time += delta * self.scale;
+        self.time
+    }
+
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    // Rewind to the start, keeping the pause state and time scale
+    fn reset(&mut self) {
+        self.time = 0.0;
+    }
+
+    // Jump `delta` seconds of animation time, not before the start
+    fn seek(&mut self, delta: f32) {
+        self.time = (self.time + delta).max(0.0);
+    }
+
+    // Negative scales run the animation backward
+    fn adjust_scale(&mut self, step: f32) {
+        self.scale = (self.scale + step).clamp(-MAX_TIME_SCALE, MAX_TIME_SCALE);
+    }
+}
+
+// Mirrors the blit shader's `Post` struct, padded to 16 bytes
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct PostUniforms {
+    exposure: f32,
+    tonemap: u32,
+    _padding: [u32; 2],
+}
+
+// Format the shader pass renders into before the blit to a `format` output.
+// Tone mapping needs the colors above 1 to still be there, so it gets a float
+// texture whatever the output is.
+fn render_target_format(format: wgpu::TextureFormat, tonemap: Tonemap) -> wgpu::TextureFormat {
+    match tonemap {
+        Tonemap::None => format,
+        _ => wgpu::TextureFormat::Rgba16Float,
+    }
+}
+
+// Holds frames to `--fps-cap` when nothing else paces them. It sleeps for most
+// of the wait, which can overshoot by a millisecond or more, and spins for the
+// rest.
+#[cfg(not(target_arch = "wasm32"))]
+struct FrameLimiter {
+    interval: std::time::Duration,
+    next_frame: Instant,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FrameLimiter {
+    // Longest part of the wait that's spun rather than slept
+    const SPIN: std::time::Duration = std::time::Duration::from_millis(2);
+
+    fn new(fps: f32) -> Self {
+        Self {
+            interval: std::time::Duration::from_secs_f32(1.0 / fps),
+            next_frame: Instant::now(),
+        }
+    }
+
+    // Wait until the next frame is due. A frame that was already late starts
+    // the schedule over rather than rushing the ones after it.
+    fn wait(&mut self) {
+        let now = Instant::now();
+        if now < self.next_frame {
+            let remaining = self.next_frame - now;
+            if remaining > Self::SPIN {
+                std::thread::sleep(remaining - Self::SPIN);
+            }
+            while Instant::now() < self.next_frame {
+                std::hint::spin_loop();
+            }
+            self.next_frame += self.interval;
+        } else {
+            self.next_frame = now + self.interval;
+        }
+    }
+}
+
+// Everything needed to draw the shader into a texture view, independent of
+// whether that view comes from a window surface or an offscreen texture.
+//
+// The shader doesn't draw into the view directly: it renders into one of two
+// feedback textures while the other, holding the previous frame, is bound as
+// `prev_frame` at binding 1 with a linear sampler at binding 2. The result is
+// then blitted to the view and the textures swap roles for the next frame.
+// The blit is also where exposure and tone mapping are applied, with their
+// settings in a uniform buffer of its own at blit binding 2.
+// The `--channelN` inputs are bound as `channel0` to `channel3` where
+// `channel::binding` puts them, 3 and then 6 to 8, all read with the sampler
+// at binding 4. Only as many are bound as configured, and `channel0` always is.
+// The blue noise texture the built-in shader's grain can use is always bound
+// at 9.
+//
+// With `--compute` a compute shader fills the feedback texture instead,
+// through a write-only storage binding at 5, and the rest stays the same.
+//
+// When the device has push constants the shader's `uniforms` variable is
+// turned into a push constant block and the values are recorded straight into
+// the render pass, which saves a buffer write and a bind group indirection per
+// frame. The uniform buffer stays bound either way so the layout is the same.
+//
+// The shader pass's bind group layout is built from what the shader declares,
+// see `bindings::reflect`, and rebuilt when a new shader declares different
+// resources.
+// Compute shaders always read the buffer.
+struct Renderer {
+    uniform_buffer: wgpu::Buffer,
+    push_constants: bool,
+    uniforms: Uniforms,
+    // What the shader pass binds, and the layout holding it
+    resources: Vec<Resource>,
+    // Set when the shader reads `keys`, which then don't trigger shortcuts
+    reads_keys: bool,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    channels: Vec<Channel>,
+    channel_sampler: wgpu::Sampler,
+    blue_noise: texture::Texture,
+    pipeline_layout: wgpu::PipelineLayout,
+    vertex_shader: wgpu::ShaderModule,
+    geometry: FullscreenGeometry,
+    // Winding and culling of the shader pass, see --cull and --front-face
+    primitive: wgpu::PrimitiveState,
+    // Set with --depth, along with the buffer the test runs against
+    depth_stencil: Option<wgpu::DepthStencilState>,
+    depth_view: Option<wgpu::TextureView>,
+    pipeline: ShaderPipeline,
+    blit_bind_group_layout: wgpu::BindGroupLayout,
+    blit_pipeline: wgpu::RenderPipeline,
+    // One pipeline per `--post` effect, in order, and the textures they take
+    // turns drawing into with the bind groups that read them
+    post_pipelines: Vec<wgpu::RenderPipeline>,
+    post_views: Vec<wgpu::TextureView>,
+    post_bind_groups: Vec<wgpu::BindGroup>,
+    post_buffer: wgpu::Buffer,
+    tonemap: Tonemap,
+    // Format of the output the blit draws into, which captures are read back
+    // in, and of the feedback textures the shader pass renders into
+    #[cfg(not(target_arch = "wasm32"))]
+    format: wgpu::TextureFormat,
+    target_format: wgpu::TextureFormat,
+    sample_count: u32,
+    // Multisampled target that gets resolved into the feedback texture, when
+    // MSAA is on
+    msaa_view: Option<wgpu::TextureView>,
+    // Render targets for each feedback texture, with the bind groups that read
+    // the other one as the previous frame and the ones that blit it out
+    feedback_views: Vec<wgpu::TextureView>,
+    bind_groups: Vec<wgpu::BindGroup>,
+    blit_bind_groups: Vec<wgpu::BindGroup>,
+    // Index of the feedback texture the next frame renders into
+    target: usize,
+    // Render resolution, which sets the compute dispatch size
+    size: wgpu::Extent3d,
+    // Times the shader pass on the GPU when profiling is set up
+    timer: Option<GpuTimer>,
+    // How the shader pass starts out, see --clear-color and --no-clear
+    load_op: wgpu::LoadOp<wgpu::Color>,
+    // Where each shader is written before it's compiled, see --dump-wgsl
+    #[cfg(not(target_arch = "wasm32"))]
+    dump_wgsl: Option<PathBuf>,
+}
+
+impl Renderer {
+    // The render targets start out at `uniforms.resolution`, which can differ
+    // from the size of the views drawn into with `--scale`. The rest of the
+    // setup, such as whether this is a compute shader, follows `options`.
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        fragment_source: &FragmentSource,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        uniforms: &Uniforms,
+        channels: Vec<Channel>,
+        options: &Options,
+    ) -> Result<Self, wgpu::Error> {
+        let compute = options.compute;
+        let target_format = render_target_format(format, options.tonemap);
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = &options.dump_wgsl {
+            dump_wgsl(fragment_source, compute, path);
+        }
+        let primitive = wgpu::PrimitiveState {
+            front_face: options.front_face,
+            cull_mode: options.cull_mode,
+            ..Default::default()
+        };
+        let depth_stencil = options.depth.then(|| wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        });
+
+        // Create the uniform buffer
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[*uniforms]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Create the bind group layout from what the shader declares
+        let (resources, reads_keys) = shader_resources(fragment_source, channels.len(), compute)?;
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Previous Frame Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        // Create the shader module
+        let vertex_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: wgpu::ShaderSource::Wgsl(VERTEX_SHADER.into()),
+        });
+
+        // Create the render pipeline
+        let push_constants = !compute && device.features().contains(wgpu::Features::PUSH_CONSTANTS);
+        let (bind_group_layout, pipeline_layout) =
+            create_shader_layouts(device, &resources, compute, push_constants);
+
+        let pipeline = if compute {
+            ShaderPipeline::Compute(try_create_compute_pipeline(
+                device,
+                &pipeline_layout,
+                fragment_source,
+            )?)
+        } else {
+            ShaderPipeline::Render(try_create_render_pipeline(
+                device,
+                &pipeline_layout,
+                vertex_state(&vertex_shader, options.fullscreen_geometry),
+                primitive,
+                depth_stencil.clone(),
+                fragment_source,
+                target_format,
+                sample_count,
+                push_constants,
+            )?)
+        };
+
+        // Create the pipeline that copies the finished frame to the output
+        let blit_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("blit_bind_group_layout"),
+            });
+        let blit_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Blit Shader"),
+            source: wgpu::ShaderSource::Wgsl(BLIT_SHADER.into()),
+        });
+        let blit_pipeline = create_blit_pipeline(
+            device,
+            &blit_bind_group_layout,
+            &blit_shader,
+            blit_entry_point(options.colorspace, format),
+            format,
+            options.fullscreen_geometry,
+        );
+
+        // The `--post` effects run between the shader pass and the blit
+        let post_pipelines = if options.post.is_empty() {
+            Vec::new()
+        } else {
+            let post_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Post Shader"),
+                source: wgpu::ShaderSource::Wgsl(format!("{}{}", BLIT_SHADER, POST_SHADER).into()),
+            });
+            options
+                .post
+                .iter()
+                .map(|&effect| {
+                    create_blit_pipeline(
+                        device,
+                        &blit_bind_group_layout,
+                        &post_shader,
+                        post_entry_point(effect),
+                        target_format,
+                        options.fullscreen_geometry,
+                    )
+                })
+                .collect()
+        };
+        let post_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Post Uniform Buffer"),
+            size: std::mem::size_of::<PostUniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut renderer = Self {
+            uniform_buffer,
+            push_constants,
+            uniforms: *uniforms,
+            resources,
+            reads_keys,
+            bind_group_layout,
+            sampler,
+            channels,
+            channel_sampler: texture::channel_sampler(device),
+            blue_noise: texture::Texture::blue_noise(device, queue),
+            pipeline_layout,
+            vertex_shader,
+            geometry: options.fullscreen_geometry,
+            primitive,
+            depth_stencil,
+            depth_view: None,
+            pipeline,
+            blit_bind_group_layout,
+            blit_pipeline,
+            post_pipelines,
+            post_views: Vec::new(),
+            post_bind_groups: Vec::new(),
+            post_buffer,
+            tonemap: options.tonemap,
+            #[cfg(not(target_arch = "wasm32"))]
+            format,
+            target_format,
+            sample_count,
+            msaa_view: None,
+            feedback_views: Vec::new(),
+            bind_groups: Vec::new(),
+            blit_bind_groups: Vec::new(),
+            target: 0,
+            size: wgpu::Extent3d::default(),
+            timer: None,
+            load_op: options.load_op,
+            #[cfg(not(target_arch = "wasm32"))]
+            dump_wgsl: options.dump_wgsl.clone(),
+        };
+        let [width, height] = uniforms.resolution;
+        renderer.resize(device, width as u32, height as u32);
+        renderer.update(queue, uniforms);
+        Ok(renderer)
+    }
+
+    // Recreate the render targets for a new render resolution. The feedback
+    // textures start out cleared, so the previous frame is black after a resize.
+    fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        let size = wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        };
+
+        self.msaa_view = (self.sample_count > 1).then(|| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("MSAA Texture"),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: self.sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: self.target_format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+
+        self.depth_view = self.depth_stencil.is_some().then(|| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("Depth Texture"),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: self.sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: DEPTH_FORMAT,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+
+        let compute = matches!(self.pipeline, ShaderPipeline::Compute(_));
+        let (format, usage) = if compute {
+            (COMPUTE_FORMAT, wgpu::TextureUsages::STORAGE_BINDING)
+        } else {
+            (self.target_format, wgpu::TextureUsages::RENDER_ATTACHMENT)
+        };
+        self.feedback_views = (0..2)
+            .map(|_| {
+                device
+                    .create_texture(&wgpu::TextureDescriptor {
+                        label: Some("Feedback Texture"),
+                        size,
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format,
+                        usage: usage | wgpu::TextureUsages::TEXTURE_BINDING,
+                        view_formats: &[],
+                    })
+                    .create_view(&wgpu::TextureViewDescriptor::default())
+            })
+            .collect();
+
+        self.bind_groups = self.create_bind_groups(device);
+        self.size = size;
+
+        self.blit_bind_groups = self
+            .feedback_views
+            .iter()
+            .map(|view| self.create_blit_bind_group(device, view))
+            .collect();
+
+        // Two textures are enough for any number of effects to alternate between
+        self.post_views = (0..self.post_pipelines.len().min(2))
+            .map(|_| {
+                device
+                    .create_texture(&wgpu::TextureDescriptor {
+                        label: Some("Post Texture"),
+                        size,
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format: self.target_format,
+                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                            | wgpu::TextureUsages::TEXTURE_BINDING,
+                        view_formats: &[],
+                    })
+                    .create_view(&wgpu::TextureViewDescriptor::default())
+            })
+            .collect();
+        self.post_bind_groups = self
+            .post_views
+            .iter()
+            .map(|view| self.create_blit_bind_group(device, view))
+            .collect();
+    }
+
+    // The shader pass's bind groups, one per feedback texture. Rendering into
+    // texture `i` reads the other one as the previous frame.
+    fn create_bind_groups(&self, device: &wgpu::Device) -> Vec<wgpu::BindGroup> {
+        (0..2)
+            .map(|i| {
+                let previous_frame = &self.feedback_views[1 - i];
+                let entries = self
+                    .resources
+                    .iter()
+                    .map(|&resource| {
+                        let resource_binding = match resource {
+                            Resource::Uniforms => self.uniform_buffer.as_entire_binding(),
+                            Resource::PreviousFrame => {
+                                wgpu::BindingResource::TextureView(previous_frame)
+                            }
+                            Resource::PreviousFrameSampler => {
+                                wgpu::BindingResource::Sampler(&self.sampler)
+                            }
+                            Resource::Channel(index) => {
+                                wgpu::BindingResource::TextureView(match &self.channels[index] {
+                                    Channel::Texture(texture) => &texture.view,
+                                    Channel::PreviousFrame => previous_frame,
+                                })
+                            }
+                            Resource::ChannelSampler => {
+                                wgpu::BindingResource::Sampler(&self.channel_sampler)
+                            }
+                            Resource::ComputeOutput => {
+                                wgpu::BindingResource::TextureView(&self.feedback_views[i])
+                            }
+                            Resource::BlueNoise => {
+                                wgpu::BindingResource::TextureView(&self.blue_noise.view)
+                            }
+                        };
+                        wgpu::BindGroupEntry {
+                            binding: resource.binding(),
+                            resource: resource_binding,
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: &self.bind_group_layout,
+                    entries: &entries,
+                    label: Some("bind_group"),
+                })
+            })
+            .collect()
+    }
+
+    // Bind group for the blit or a post effect to read `view`
+    fn create_blit_bind_group(
+        &self,
+        device: &wgpu::Device,
+        view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.blit_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.post_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("blit_bind_group"),
+        })
+    }
+
+    fn update(&mut self, queue: &wgpu::Queue, uniforms: &Uniforms) {
+        self.uniforms = *uniforms;
+        if !self.push_constants {
+            queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[*uniforms]));
+        }
+        let post = PostUniforms {
+            exposure: uniforms.exposure,
+            tonemap: self.tonemap as u32,
+            _padding: [0; 2],
+        };
+        queue.write_buffer(&self.post_buffer, 0, bytemuck::bytes_of(&post));
+    }
+
+    // Record the shader pass and the blit that copies its result into `view`
+    fn draw(&mut self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let feedback_view = &self.feedback_views[self.target];
+
+        // With MSAA the multisampled target is drawn to and resolved into the
+        // feedback texture
+        let (target_view, resolve_target) = match &self.msaa_view {
+            Some(msaa_view) => (msaa_view, Some(feedback_view)),
+            None => (feedback_view, None),
+        };
+
+        if let Some(timer) = &mut self.timer {
+            timer.begin(encoder);
+        }
+        match &self.pipeline {
+            ShaderPipeline::Render(pipeline) => {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Render Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: target_view,
+                        resolve_target,
+                        ops: wgpu::Operations {
+                            load: self.load_op,
+                            store: true,
+                        },
+                    })],
+                    // Depth only matters within the frame
+                    depth_stencil_attachment: self.depth_view.as_ref().map(|view| {
+                        wgpu::RenderPassDepthStencilAttachment {
+                            view,
+                            depth_ops: Some(wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(1.0),
+                                store: false,
+                            }),
+                            stencil_ops: None,
+                        }
+                    }),
+                });
+
+                render_pass.set_pipeline(pipeline);
+                render_pass.set_bind_group(0, &self.bind_groups[self.target], &[]);
+                if self.push_constants {
+                    render_pass.set_push_constants(
+                        wgpu::ShaderStages::FRAGMENT,
+                        0,
+                        bytemuck::bytes_of(&self.uniforms),
+                    );
+                }
+                render_pass.draw(0..fullscreen_vertices(self.geometry).1, 0..1);
+            }
+            ShaderPipeline::Compute(pipeline) => {
+                let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("Compute Pass"),
+                });
+                compute_pass.set_pipeline(pipeline);
+                compute_pass.set_bind_group(0, &self.bind_groups[self.target], &[]);
+                // Round up so partial workgroups cover the right and bottom edges
+                compute_pass.dispatch_workgroups(
+                    self.size.width.div_ceil(WORKGROUP_SIZE),
+                    self.size.height.div_ceil(WORKGROUP_SIZE),
+                    1,
+                );
+            }
+        }
+        if let Some(timer) = &mut self.timer {
+            timer.end(encoder);
+        }
+
+        self.target = 1 - self.target;
+
+        // Each effect reads what the one before it drew, starting with the frame
+        let mut source = &self.blit_bind_groups[1 - self.target];
+        for (i, pipeline) in self.post_pipelines.iter().enumerate() {
+            let mut post_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Post Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.post_views[i % 2],
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            post_pass.set_pipeline(pipeline);
+            post_pass.set_bind_group(0, source, &[]);
+            post_pass.draw(0..fullscreen_vertices(self.geometry).1, 0..1);
+            source = &self.post_bind_groups[i % 2];
+        }
+
+        self.blit(encoder, view);
+    }
+
+    // Bind group reading the last frame drawn, after the post effects
+    fn output_bind_group(&self) -> &wgpu::BindGroup {
+        match self.post_pipelines.len() {
+            0 => &self.blit_bind_groups[1 - self.target],
+            count => &self.post_bind_groups[(count - 1) % 2],
+        }
+    }
+
+    // Record a blit of the last frame drawn into `view`
+    fn blit(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let mut blit_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Blit Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        blit_pass.set_pipeline(&self.blit_pipeline);
+        blit_pass.set_bind_group(0, self.output_bind_group(), &[]);
+        blit_pass.draw(0..fullscreen_vertices(self.geometry).1, 0..1);
+    }
+
+    // Rebuild the pipeline around a new shader of the same kind, with new
+    // layouts if it declares different resources. On failure the current
+    // pipeline is kept, so drawing carries on with the last shader that compiled.
+    fn set_fragment(
+        &mut self,
+        device: &wgpu::Device,
+        source: &FragmentSource,
+    ) -> Result<(), wgpu::Error> {
+        let compute = matches!(self.pipeline, ShaderPipeline::Compute(_));
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = &self.dump_wgsl {
+            dump_wgsl(source, compute, path);
+        }
+        let (resources, reads_keys) = shader_resources(source, self.channels.len(), compute)?;
+        let layouts = (resources != self.resources)
+            .then(|| create_shader_layouts(device, &resources, compute, self.push_constants));
+        let pipeline_layout = layouts
+            .as_ref()
+            .map_or(&self.pipeline_layout, |(_, pipeline_layout)| {
+                pipeline_layout
+            });
+
+        self.pipeline = if compute {
+            ShaderPipeline::Compute(try_create_compute_pipeline(
+                device,
+                pipeline_layout,
+                source,
+            )?)
+        } else {
+            ShaderPipeline::Render(try_create_render_pipeline(
+                device,
+                pipeline_layout,
+                vertex_state(&self.vertex_shader, self.geometry),
+                self.primitive,
+                self.depth_stencil.clone(),
+                source,
+                self.target_format,
+                self.sample_count,
+                self.push_constants,
+            )?)
+        };
+        if let Some((bind_group_layout, pipeline_layout)) = layouts {
+            self.resources = resources;
+            self.bind_group_layout = bind_group_layout;
+            self.pipeline_layout = pipeline_layout;
+            self.bind_groups = self.create_bind_groups(device);
+        }
+        self.reads_keys = reads_keys;
+        Ok(())
+    }
+
+    // Re-read `source` from disk if it came from a file and switch to it,
+    // reporting any problem instead of failing. `source` takes the new code
+    // once it compiles, and the new list of files as soon as it's read.
+    fn reload(&mut self, device: &wgpu::Device, source: &mut FragmentSource) -> bool {
+        let reread = match source.reread() {
+            Ok(reread) => reread,
+            Err(err) => {
+                log::error!("Failed to read shader '{}': {}", source.name, err);
+                return false;
+            }
+        };
+        source.files = reread.files.clone();
+
+        match self.set_fragment(device, &reread) {
+            Ok(()) => {
+                *source = reread;
+                true
+            }
+            Err(err) => {
+                log::error!("Failed to compile shader '{}':\n{}", reread.name, err);
+                false
+            }
+        }
+    }
+}
+
+// The pipeline that fills the feedback texture each frame
+enum ShaderPipeline {
+    Render(wgpu::RenderPipeline),
+    Compute(wgpu::ComputePipeline),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ShaderLanguage {
+    Wgsl,
+    Glsl,
+}
+
+impl ShaderLanguage {
+    // GLSL files are recognized by extension, anything else is taken as WGSL
+    fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("glsl" | "frag" | "comp") => ShaderLanguage::Glsl,
+            _ => ShaderLanguage::Wgsl,
+        }
+    }
+
+    fn entry_point(self) -> &'static str {
+        match self {
+            ShaderLanguage::Wgsl => "fs_main",
+            ShaderLanguage::Glsl => "main",
+        }
+    }
+
+    fn compute_entry_point(self) -> &'static str {
+        match self {
+            ShaderLanguage::Wgsl => "cs_main",
+            ShaderLanguage::Glsl => "main",
+        }
+    }
+}
+
+// Fragment shader code together with the language it's written in. GLSL
+// shaders declare the uniforms as
+// `layout(set = 0, binding = 0) uniform Uniforms { ... };` with the same
+// fields as the WGSL struct.
+#[derive(Clone)]
+struct FragmentSource {
+    // Preset name or file path, for messages and the window title
+    name: String,
+    code: String,
+    language: ShaderLanguage,
+    // Set for shaders loaded from disk
+    path: Option<PathBuf>,
+    // `--fs-entry`, or `None` for the language's usual name
+    entry_point: Option<String>,
+    // `--vs-entry`, or `None` to use the built-in vertex shader
+    vertex_entry_point: Option<String>,
+    // Set when `code` is a `mainImage` wrapped by `--shadertoy`
+    shadertoy: bool,
+    // Every file `code` was read from, `path` and its `#include`s
+    files: Vec<PathBuf>,
+}
+
+impl FragmentSource {
+    fn preset(index: usize) -> Self {
+        let (name, code) = PRESETS[index];
+        Self {
+            name: name.to_string(),
+            code: code.to_string(),
+            language: ShaderLanguage::Wgsl,
+            path: None,
+            entry_point: None,
+            vertex_entry_point: None,
+            shadertoy: false,
+            files: Vec::new(),
+        }
+    }
+
+    // The built-in pattern as a compute shader
+    #[cfg(not(target_arch = "wasm32"))]
+    fn compute() -> Self {
+        Self {
+            name: "Psychedelic (compute)".to_string(),
+            code: format!("{}{}", FRAGMENT_SHADER, COMPUTE_ENTRY),
+            language: ShaderLanguage::Wgsl,
+            path: None,
+            entry_point: None,
+            vertex_entry_point: None,
+            shadertoy: false,
+            files: Vec::new(),
+        }
+    }
+
+    // Read the shader with its `#include`s spliced in
+    fn load(path: &Path) -> Result<Self, String> {
+        let (code, files) = include::expand(path)?;
+        Ok(Self {
+            name: path.display().to_string(),
+            code,
+            language: ShaderLanguage::from_path(path),
+            path: Some(path.to_path_buf()),
+            entry_point: None,
+            vertex_entry_point: None,
+            shadertoy: false,
+            files,
+        })
+    }
+
+    // Wrap the shader's Shadertoy-style `mainImage` in an `fs_main`
+    fn shadertoy(self) -> Result<Self, String> {
+        if self.language != ShaderLanguage::Wgsl {
+            return Err("--shadertoy only wraps WGSL shaders".to_string());
+        }
+        Ok(Self {
+            code: shadertoy::wrap(&self.code),
+            shadertoy: true,
+            ..self
+        })
+    }
+
+    // Fetch the latest version of the shader; only files can change
+    fn reread(&self) -> Result<Self, String> {
+        match &self.path {
+            Some(path) => {
+                let source = Self {
+                    entry_point: self.entry_point.clone(),
+                    vertex_entry_point: self.vertex_entry_point.clone(),
+                    ..Self::load(path)?
+                };
+                if self.shadertoy {
+                    source.shadertoy()
+                } else {
+                    Ok(source)
+                }
+            }
+            None => Ok(self.clone()),
+        }
+    }
+
+    // The source as a naga module, or `None` if it doesn't parse. GLSL needs
+    // to know the `stage` it's written for.
+    fn parse(&self, stage: naga::ShaderStage) -> Option<naga::Module> {
+        match self.language {
+            ShaderLanguage::Wgsl => naga::front::wgsl::parse_str(&self.code).ok(),
+            ShaderLanguage::Glsl => naga::front::glsl::Frontend::default()
+                .parse(&stage.into(), &self.code)
+                .ok(),
+        }
+    }
+
+    fn fragment_entry_point(&self) -> &str {
+        self.entry_point
+            .as_deref()
+            .unwrap_or(self.language.entry_point())
+    }
+
+    // Check that the entry points to draw with exist, listing the ones that do
+    // if not. Sources that don't parse are left for wgpu to report, with its
+    // annotated errors.
+    fn check_entry_points(&self) -> Result<(), String> {
+        if self.vertex_entry_point.is_some() && self.language != ShaderLanguage::Wgsl {
+            return Err("--vs-entry needs a WGSL shader".to_string());
+        }
+        let Some(module) = self.parse(naga::ShaderStage::Fragment) else {
+            return Ok(());
+        };
+
+        let wanted = [
+            (
+                naga::ShaderStage::Fragment,
+                Some(self.fragment_entry_point()),
+            ),
+            (
+                naga::ShaderStage::Vertex,
+                self.vertex_entry_point.as_deref(),
+            ),
+        ];
+        for (stage, name) in wanted {
+            let Some(name) = name else {
+                continue;
+            };
+            if module
+                .entry_points
+                .iter()
+                .any(|entry| entry.stage == stage && entry.name == name)
+            {
+                continue;
+            }
+            let available = module
+                .entry_points
+                .iter()
+                .map(|entry| format!("{} ({:?})", entry.name, entry.stage).to_lowercase())
+                .collect::<Vec<_>>();
+            let available = if available.is_empty() {
+                "it has none".to_string()
+            } else {
+                format!("it has {}", available.join(", "))
+            };
+            return Err(format!(
+                "no {} entry point named '{}', {}",
+                format!("{:?}", stage).to_lowercase(),
+                name,
+                available
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Copies a feedback texture to the output. With `--scale` the two differ in
+// size, so the texture is sampled with a linear filter rather than loaded, and
+// averaged over each output pixel when it's the larger one.
+// On the way the colors are scaled by the exposure and tone mapped.
+const BLIT_SHADER: &str = r#"
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// `exposure` is in stops and `tonemap` holds an `options::Tonemap`
+struct Post {
+    exposure: f32,
+    tonemap: u32,
+};
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+@group(0) @binding(2)
+var<uniform> post: Post;
+
+// John Hable's operator from Uncharted 2
+fn hable(x: vec3<f32>) -> vec3<f32> {
+    let a = 0.15;
+    let b = 0.50;
+    let c = 0.10;
+    let d = 0.20;
+    let e = 0.02;
+    let f = 0.30;
+    return (x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f) - e / f;
+}
+
+fn tonemap(color: vec3<f32>) -> vec3<f32> {
+    switch post.tonemap {
+        case 1u {
+            return color / (1.0 + color);
+        }
+        case 2u {
+            // Krzysztof Narkowicz's fit of the ACES filmic curve
+            let x = color * 0.6;
+            return clamp(
+                (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+                vec3<f32>(0.0),
+                vec3<f32>(1.0)
+            );
+        }
+        case 3u {
+            // Scaled so that a white point of 11.2 maps to 1
+            let exposure_bias = 2.0;
+            return hable(color * exposure_bias) / hable(vec3<f32>(11.2));
+        }
+        default {
+            return color;
+        }
+    }
+}
+
+// The frame at `uv`, exposed and tone mapped. Float textures keep negative
+// colors, which the operators aren't meant for.
+fn sample_frame(uv: vec2<f32>) -> vec4<f32> {
+    let color = filter_frame(uv);
+    let exposed = max(color.rgb, vec3<f32>(0.0)) * exp2(post.exposure);
+    return vec4<f32>(tonemap(exposed), color.a);
+}
+
+// The frame averaged over the output pixel at `uv`. Above a `--scale` of 1 that
+// covers several texels, which a grid of bilinear taps two texels apart boxes
+// in, as each tap averages the 2x2 texels around it. A single tap does below 2.
+fn filter_frame(uv: vec2<f32>) -> vec4<f32> {
+    let size = vec2<f32>(textureDimensions(source));
+    let footprint = size * abs(vec2<f32>(dpdx(uv).x, dpdy(uv).y));
+    let taps = vec2<i32>(clamp(ceil(footprint * 0.5 - 0.01), vec2<f32>(1.0), vec2<f32>(4.0)));
+    var color = vec4<f32>(0.0);
+    for (var y = 0; y < taps.y; y += 1) {
+        for (var x = 0; x < taps.x; x += 1) {
+            let tap = vec2<f32>(vec2<i32>(x, y)) - 0.5 * vec2<f32>(taps - 1);
+            color += textureSampleLevel(source, source_sampler, uv + tap * 2.0 / size, 0.0);
+        }
+    }
+    return color / f32(taps.x * taps.y);
+}
+
+fn vertex_output(pos: vec2<f32>) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = vec4<f32>(pos, 0.0, 1.0);
+    out.uv = vec2<f32>(pos.x * 0.5 + 0.5, 0.5 - pos.y * 0.5);
+    return out;
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var pos = array<vec2<f32>, 3>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(3.0, -1.0),
+        vec2<f32>(-1.0, 3.0)
+    );
+    return vertex_output(pos[vertex_index]);
+}
+
+@vertex
+fn vs_quad(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var pos = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0)
+    );
+    return vertex_output(pos[vertex_index]);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return sample_frame(in.uv);
+}
+
+// Gamma encode for `--colorspace srgb` on a surface that doesn't do it
+@fragment
+fn fs_encode(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = sample_frame(in.uv);
+    let low = color.rgb * 12.92;
+    let high = 1.055 * pow(color.rgb, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return vec4<f32>(select(high, low, color.rgb <= vec3<f32>(0.0031308)), color.a);
+}
+
+// Undo the surface's gamma encoding for `--colorspace linear`
+@fragment
+fn fs_decode(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = sample_frame(in.uv);
+    let low = color.rgb / 12.92;
+    let high = pow((color.rgb + 0.055) / 1.055, vec3<f32>(2.4));
+    return vec4<f32>(select(high, low, color.rgb <= vec3<f32>(0.04045)), color.a);
+}
+"#;
+
+// The blit entry point that shows the shader's colors as `colorspace` says
+// on a `format` target. Shaders write linear colors by default, which sRGB
+// formats encode on their own and float formats take as they are, so only the
+// mismatched cases need the blit to convert.
+fn blit_entry_point(colorspace: Colorspace, format: wgpu::TextureFormat) -> &'static str {
+    let linear_target = format.is_srgb() || format == wgpu::TextureFormat::Rgba16Float;
+    match (colorspace, linear_target) {
+        (Colorspace::Srgb, false) => "fs_encode",
+        (Colorspace::Linear, true) => "fs_decode",
+        _ => "fs_main",
+    }
+}
+
+// Appended to `BLIT_SHADER` for the `--post` effects, one entry point each.
+// They read the frame as it is and write to a texture of the same format, so
+// any order works and exposure and tone mapping still come last, in the blit.
+const POST_SHADER: &str = r#"
+// Darken towards the corners
+@fragment
+fn fs_vignette(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source, source_sampler, in.uv);
+    // 0 at the center and 1 in the corners
+    let dist = distance(in.uv, vec2<f32>(0.5)) * 1.4142;
+    let shade = 1.0 - 0.6 * smoothstep(0.4, 1.0, dist);
+    return vec4<f32>(color.rgb * shade, color.a);
+}
+
+// Darken every third row of pixels, like a CRT
+@fragment
+fn fs_scanlines(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source, source_sampler, in.uv);
+    let shade = 0.8 + 0.2 * cos(in.position.y * 2.0944);
+    return vec4<f32>(color.rgb * shade, color.a);
+}
+
+// Pull red outwards and blue inwards, more so towards the edges, like a lens
+// that doesn't focus every wavelength at once
+@fragment
+fn fs_chromatic_aberration(in: VertexOutput) -> @location(0) vec4<f32> {
+    let offset = (in.uv - vec2<f32>(0.5)) * 0.008;
+    let color = textureSample(source, source_sampler, in.uv);
+    let r = textureSample(source, source_sampler, in.uv - offset).r;
+    let b = textureSample(source, source_sampler, in.uv + offset).b;
+    return vec4<f32>(r, color.g, b, color.a);
+}
+"#;
+
+fn post_entry_point(effect: PostEffect) -> &'static str {
+    match effect {
+        PostEffect::Vignette => "fs_vignette",
+        PostEffect::Scanlines => "fs_scanlines",
+        PostEffect::ChromaticAberration => "fs_chromatic_aberration",
+    }
+}
+
+// A pipeline that draws `entry_point` of `shader` over a `format` target,
+// reading a texture through the blit bind group layout
+fn create_blit_pipeline(
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    shader: &wgpu::ShaderModule,
+    entry_point: &str,
+    format: wgpu::TextureFormat,
+    geometry: FullscreenGeometry,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Blit Pipeline Layout"),
+        bind_group_layouts: &[bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Blit Pipeline"),
+        layout: Some(&layout),
+        vertex: vertex_state(shader, geometry),
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point,
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+// The WGSL `source` is compiled from: its code as wgpu gets it, with the
+// `#include`s expanded, or for GLSL what naga translates it to
+#[cfg(not(target_arch = "wasm32"))]
+fn shader_wgsl(source: &FragmentSource, compute: bool) -> Result<String, String> {
+    if source.language == ShaderLanguage::Wgsl {
+        return Ok(source.code.clone());
+    }
+    let stage = if compute {
+        naga::ShaderStage::Compute
+    } else {
+        naga::ShaderStage::Fragment
+    };
+    let module = source
+        .parse(stage)
+        .ok_or_else(|| "the GLSL doesn't parse, see the compile error".to_string())?;
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .map_err(|err| format!("the GLSL doesn't validate: {}", err))?;
+    naga::back::wgsl::write_string(&module, &info, naga::back::wgsl::WriterFlags::empty())
+        .map_err(|err| err.to_string())
+}
+
+// Write `source` to `path` as WGSL, reporting rather than failing, since it's
+// only there to look at
+#[cfg(not(target_arch = "wasm32"))]
+fn dump_wgsl(source: &FragmentSource, compute: bool, path: &Path) {
+    let result = shader_wgsl(source, compute)
+        .and_then(|wgsl| std::fs::write(path, wgsl).map_err(|err| err.to_string()));
+    match result {
+        Ok(()) => log::info!("Wrote shader '{}' to '{}'", source.name, path.display()),
+        Err(err) => log::error!(
+            "Failed to write shader '{}' to '{}': {}",
+            source.name,
+            path.display(),
+            err
+        ),
+    }
+}
+
+// The stage of the shader pass
+fn shader_stage(compute: bool) -> naga::ShaderStage {
+    if compute {
+        naga::ShaderStage::Compute
+    } else {
+        naga::ShaderStage::Fragment
+    }
+}
+
+// The resources the shader pass binds for `source`, see `bindings::reflect`,
+// and whether it reads `keys`, see `bindings::reads_keys`. Sources naga can't
+// parse get everything bound and are left to fail to compile.
+fn shader_resources(
+    source: &FragmentSource,
+    channels: usize,
+    compute: bool,
+) -> Result<(Vec<Resource>, bool), wgpu::Error> {
+    match source.parse(shader_stage(compute)) {
+        Some(module) => {
+            let resources =
+                bindings::reflect(&module, channels, compute).map_err(validation_error)?;
+            Ok((resources, bindings::reads_keys(&module)))
+        }
+        None => Ok((bindings::all(channels, compute), false)),
+    }
+}
+
+// The bind group layout holding `resources` and a pipeline layout around it,
+// with room for the uniforms in push constants if they're used
+fn create_shader_layouts(
+    device: &wgpu::Device,
+    resources: &[Resource],
+    compute: bool,
+    push_constants: bool,
+) -> (wgpu::BindGroupLayout, wgpu::PipelineLayout) {
+    let visibility = if compute {
+        wgpu::ShaderStages::COMPUTE
+    } else {
+        wgpu::ShaderStages::FRAGMENT
+    };
+    let entries = resources
+        .iter()
+        .map(|resource| resource.layout_entry(visibility))
+        .collect::<Vec<_>>();
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &entries,
+        label: Some("bind_group_layout"),
+    });
+
+    let push_constant_range = wgpu::PushConstantRange {
+        stages: wgpu::ShaderStages::FRAGMENT,
+        range: 0..std::mem::size_of::<Uniforms>() as u32,
+    };
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Render Pipeline Layout"),
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: if push_constants {
+            std::slice::from_ref(&push_constant_range)
+        } else {
+            &[]
+        },
+    });
+    (bind_group_layout, pipeline_layout)
+}
+
+// A problem with a shader found before it reaches wgpu, reported the same way
+// as wgpu's own validation errors
+fn validation_error(description: String) -> wgpu::Error {
+    wgpu::Error::Validation {
+        source: Box::<dyn std::error::Error + Send + Sync>::from(description.clone()),
+        description,
+    }
+}
+
+// Parse the fragment shader and move the uniform at group 0, binding 0 into
+// push constants, so shaders are written the same way on every device. `None`
+// means there's nothing to move, or the source doesn't parse and should go
+// through wgpu as is to get its annotated error.
+fn push_constant_module(fragment_source: &FragmentSource) -> Option<naga::Module> {
+    let mut module = fragment_source.parse(naga::ShaderStage::Fragment)?;
+
+    let uniforms_binding = naga::ResourceBinding {
+        group: 0,
+        binding: 0,
+    };
+    let (_, global) = module.global_variables.iter_mut().find(|(_, global)| {
+        global.space == naga::AddressSpace::Uniform
+            && global.binding.as_ref() == Some(&uniforms_binding)
+    })?;
+    global.space = naga::AddressSpace::PushConstant;
+    global.binding = None;
+    Some(module)
+}
+
+// The vertex stage of a shader pass drawn with `geometry`
+fn vertex_state(
+    module: &wgpu::ShaderModule,
+    geometry: FullscreenGeometry,
+) -> wgpu::VertexState<'_> {
+    wgpu::VertexState {
+        module,
+        entry_point: fullscreen_vertices(geometry).0,
+        buffers: &[],
+    }
+}
+
+// Build the pipeline inside a validation error scope, so a shader that doesn't
+// compile comes back as an error carrying the compiler's annotated source
+// rather than reaching wgpu's default handler, which panics
+#[allow(clippy::too_many_arguments)]
+fn try_create_render_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    vertex: wgpu::VertexState,
+    primitive: wgpu::PrimitiveState,
+    depth_stencil: Option<wgpu::DepthStencilState>,
+    fragment_source: &FragmentSource,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    push_constants: bool,
+) -> Result<wgpu::RenderPipeline, wgpu::Error> {
+    fragment_source
+        .check_entry_points()
+        .map_err(validation_error)?;
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let pipeline = create_render_pipeline(
+        device,
+        layout,
+        vertex,
+        primitive,
+        depth_stencil,
+        fragment_source,
+        format,
+        sample_count,
+        push_constants,
+    );
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(err) => Err(err),
+        None => Ok(pipeline),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    vertex: wgpu::VertexState,
+    primitive: wgpu::PrimitiveState,
+    depth_stencil: Option<wgpu::DepthStencilState>,
+    fragment_source: &FragmentSource,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    push_constants: bool,
+) -> wgpu::RenderPipeline {
+    let module = push_constants
+        .then(|| push_constant_module(fragment_source))
+        .flatten();
+    let source = match (module, fragment_source.language) {
+        (Some(module), _) => wgpu::ShaderSource::Naga(Cow::Owned(module)),
+        (None, ShaderLanguage::Wgsl) => {
+            wgpu::ShaderSource::Wgsl(fragment_source.code.as_str().into())
+        }
+        (None, ShaderLanguage::Glsl) => wgpu::ShaderSource::Glsl {
+            shader: fragment_source.code.as_str().into(),
+            stage: naga::ShaderStage::Fragment,
+            defines: Default::default(),
+        },
+    };
+    let fragment_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Fragment Shader"),
+        source,
+    });
+
+    // The vertex stage can come from the shader itself, see --vs-entry
+    let vertex = match &fragment_source.vertex_entry_point {
+        Some(entry_point) => wgpu::VertexState {
+            module: &fragment_shader,
+            entry_point,
+            buffers: &[],
+        },
+        None => vertex,
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: Some(layout),
+        vertex,
+        fragment: Some(wgpu::FragmentState {
+            module: &fragment_shader,
+            entry_point: fragment_source.fragment_entry_point(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive,
+        depth_stencil,
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
+// Like `try_create_render_pipeline`, for `--compute` shaders
+fn try_create_compute_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    source: &FragmentSource,
+) -> Result<wgpu::ComputePipeline, wgpu::Error> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let shader_source = match source.language {
+        ShaderLanguage::Wgsl => wgpu::ShaderSource::Wgsl(source.code.as_str().into()),
+        ShaderLanguage::Glsl => wgpu::ShaderSource::Glsl {
+            shader: source.code.as_str().into(),
+            stage: naga::ShaderStage::Compute,
+            defines: Default::default(),
+        },
+    };
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Compute Shader"),
+        source: shader_source,
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("Compute Pipeline"),
+        layout: Some(layout),
+        module: &shader,
+        entry_point: source.language.compute_entry_point(),
+    });
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(err) => Err(err),
+        None => Ok(pipeline),
+    }
+}
+
+// Render a frame into an offscreen texture and read it back as tightly packed
+// RGBA8 rows. The texture uses the surface format so the pipeline can be
+// reused as is, and since sRGB formats store encoded values the bytes come back
+// exactly as they appear on screen; only the channel order needs fixing. See
+// `read_back` for `draw`.
+#[cfg(not(target_arch = "wasm32"))]
+fn capture_frame(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    renderer: &mut Renderer,
+    width: u32,
+    height: u32,
+    draw: bool,
+) -> Result<Vec<u8>, String> {
+    let swap_red_blue = swaps_red_blue(renderer.format)?;
+    let mut pixels = read_back(device, queue, renderer, width, height, draw)?;
+    if swap_red_blue {
+        for pixel in pixels.chunks_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+    Ok(pixels)
+}
+
+// Render a frame into an offscreen texture in the renderer's format and read
+// its texels back as tightly packed rows. Without `draw` the last frame drawn
+// is copied out instead, leaving the feedback textures as they are.
+#[cfg(not(target_arch = "wasm32"))]
+fn read_back(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    renderer: &mut Renderer,
+    width: u32,
+    height: u32,
+    draw: bool,
+) -> Result<Vec<u8>, String> {
+    let format = renderer.format;
+    let bytes_per_pixel = format
+        .block_size(None)
+        .ok_or_else(|| format!("reading back {:?} textures is not supported", format))?;
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Capture Texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    // Rows in a texture-to-buffer copy must be padded to a multiple of 256 bytes
+    let unpadded_bytes_per_row = width * bytes_per_pixel;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Capture Buffer"),
+        size: (padded_bytes_per_row * height) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    if draw {
+        renderer.draw(&mut encoder, &view);
+    } else {
+        renderer.blit(&mut encoder, &view);
+    }
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    let (map_tx, map_rx) = mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |res| {
+        let _ = map_tx.send(res);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    map_rx
+        .recv()
+        .map_err(|err| err.to_string())?
+        .map_err(|err| err.to_string())?;
+
+    let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+    for row in slice
+        .get_mapped_range()
+        .chunks(padded_bytes_per_row as usize)
+    {
+        pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+    }
+    buffer.unmap();
+    Ok(pixels)
+}
+
+// Render a frame, or with `draw` unset take the last one drawn, and read it
+// back as an image to save. Float frames become 16-bit images, gamma encoded
+// like the 8-bit ones and clipped to 1.
+#[cfg(not(target_arch = "wasm32"))]
+fn capture_image(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    renderer: &mut Renderer,
+    width: u32,
+    height: u32,
+    draw: bool,
+) -> Result<image::DynamicImage, String> {
+    if renderer.format != wgpu::TextureFormat::Rgba16Float {
+        let pixels = capture_frame(device, queue, renderer, width, height, draw)?;
+        let image = image::RgbaImage::from_raw(width, height, pixels)
+            .expect("readback matches the frame size");
+        return Ok(image.into());
+    }
+
+    let texels = read_back(device, queue, renderer, width, height, draw)?;
+    let samples = texels
+        .chunks_exact(2)
+        .enumerate()
+        .map(|(i, half)| {
+            let value = f16_to_f32(u16::from_le_bytes([half[0], half[1]])).clamp(0.0, 1.0);
+            // Alpha isn't gamma encoded
+            let value = if i % 4 == 3 {
+                value
+            } else {
+                srgb_encode(value)
+            };
+            (value * u16::MAX as f32).round() as u16
+        })
+        .collect();
+    let image = image::ImageBuffer::<image::Rgba<u16>, Vec<u16>>::from_raw(width, height, samples)
+        .expect("readback matches the frame size");
+    Ok(image.into())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+// The sRGB transfer function, as in the blit's `fs_encode`
+#[cfg(not(target_arch = "wasm32"))]
+fn srgb_encode(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// Whether pixels read back from a `format` texture are BGRA and need red and
+// blue swapped to make RGBA, or an error for formats that can't be captured
+#[cfg(not(target_arch = "wasm32"))]
+fn swaps_red_blue(format: wgpu::TextureFormat) -> Result<bool, String> {
+    match format {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => Ok(false),
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => Ok(true),
+        _ => Err(format!("capturing {:?} frames is not supported", format)),
+    }
+}
+
+// A new `screenshot-YYYYMMDD-HHMMSS.png` name for the current time in UTC, with
+// a counter added for screenshots taken within the same second
+#[cfg(not(target_arch = "wasm32"))]
+fn screenshot_path() -> PathBuf {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let (days, time) = (seconds / 86400, seconds % 86400);
+    let (year, month, day) = civil_from_days(days);
+    let stem = format!(
+        "screenshot-{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    );
+    let mut path = PathBuf::from(format!("{}.png", stem));
+    let mut count = 1;
+    while path.exists() {
+        count += 1;
+        path = PathBuf::from(format!("{}-{}.png", stem, count));
+    }
+    path
+}
+
+// Year, month and day of the date `days` after 1970-01-01, after Howard
+// Hinnant's `civil_from_days`, with years starting in March so leap days come
+// last
+#[cfg(not(target_arch = "wasm32"))]
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719468;
+    let era = z / 146097;
+    let day_of_era = z % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    (year_of_era + era * 400 + u64::from(month <= 2), month, day)
+}
+
+// Save the frame last drawn, as it's on screen, without drawing another that
+// would move shaders reading the previous frame along
+#[cfg(not(target_arch = "wasm32"))]
+fn save_png(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    renderer: &mut Renderer,
+    width: u32,
+    height: u32,
+    path: &Path,
+) -> Result<(), String> {
+    capture_image(device, queue, renderer, width, height, false)?
+        .save(path)
+        .map_err(|err| err.to_string())
+}
+
+// Watches a shader's files for edits, the main one and everything it
+// includes. It watches the directories containing them rather than the files
+// themselves, since many editors save by replacing the file, which would drop
+// a direct watch.
+#[cfg(not(target_arch = "wasm32"))]
+struct ShaderWatcher {
+    watcher: RecommendedWatcher,
+    // The files, as `watched_path` gives them, shared with the event handler
+    files: Arc<Mutex<Vec<PathBuf>>>,
+    dirs: Vec<PathBuf>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ShaderWatcher {
+    fn new(files: &[PathBuf], reload_tx: mpsc::Sender<()>) -> Option<Self> {
+        let watched = Arc::new(Mutex::new(Vec::new()));
+        let handler_files = Arc::clone(&watched);
+        let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            if let Ok(event) = res {
+                let files = handler_files.lock().unwrap();
+                let touches_shader = event
+                    .paths
+                    .iter()
+                    .any(|path| files.contains(&watched_path(path)));
+                if touches_shader && (event.kind.is_modify() || event.kind.is_create()) {
+                    let _ = reload_tx.send(());
+                }
+            }
+        });
+
+        match watcher {
+            Ok(watcher) => {
+                let mut watcher = Self {
+                    watcher,
+                    files: watched,
+                    dirs: Vec::new(),
+                };
+                watcher.watch(files);
+                Some(watcher)
+            }
+            Err(err) => {
+                log::warn!("Failed to watch shader files: {}", err);
+                None
+            }
+        }
+    }
+
+    // Switch to watching `files`, such as after a reload changes the includes
+    fn watch(&mut self, files: &[PathBuf]) {
+        let mut dirs = Vec::new();
+        for file in files {
+            let dir = match file.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+                _ => PathBuf::from("."),
+            };
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+
+        for dir in self.dirs.iter().filter(|dir| !dirs.contains(dir)) {
+            let _ = self.watcher.unwatch(dir);
+        }
+        for dir in dirs.iter().filter(|dir| !self.dirs.contains(dir)) {
+            if let Err(err) = self.watcher.watch(dir, RecursiveMode::NonRecursive) {
+                log::warn!("Failed to watch '{}': {}", dir.display(), err);
+            }
+        }
+        self.dirs = dirs;
+        *self.files.lock().unwrap() = files.iter().map(|file| watched_path(file)).collect();
+    }
+}
+
+// `path` with its directory resolved, so a file matches however it was
+// reached. The file itself may not exist while an editor replaces it.
+#[cfg(not(target_arch = "wasm32"))]
+fn watched_path(path: &Path) -> PathBuf {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let dir = std::fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    match path.file_name() {
+        Some(name) => dir.join(name),
+        None => dir,
+    }
+}
+
+// How often the FPS overlay refreshes, in seconds
+const STATS_INTERVAL: f32 = 0.5;
+
+// Seconds between the GPU times printed with --verbose
+const GPU_REPORT_INTERVAL: f32 = 1.0;
+
+// Frames counted towards the overlay's average frame time
+struct FrameStats {
+    frames: u32,
+    time: f32,
+}
+
+impl FrameStats {
+    fn new() -> Self {
+        Self {
+            frames: 0,
+            time: 0.0,
+        }
+    }
+
+    // Count a frame that took `delta` seconds. Every `STATS_INTERVAL` this
+    // returns the average frame time over it and starts counting afresh.
+    fn update(&mut self, delta: f32) -> Option<f32> {
+        self.frames += 1;
+        self.time += delta;
+        if self.time < STATS_INTERVAL {
+            return None;
+        }
+        let frame_time = self.time / self.frames as f32;
+        *self = Self::new();
+        Some(frame_time)
+    }
+}
+
+// `--title` if given, otherwise one naming the current shader
+fn window_title(options: &Options, source: &FragmentSource) -> String {
+    match &options.title {
+        Some(title) => title.clone(),
+        None => format!("Psychedelic WGPU Shader - {}", source.name),
+    }
+}
+
+// Resolution the shader renders at for an output of `width` x `height`, given
+// `--scale`. It's kept within the device's texture size limit.
+fn render_size(device: &wgpu::Device, width: u32, height: u32, scale: f32) -> (u32, u32) {
+    let max = device.limits().max_texture_dimension_2d;
+    let scaled = |size: u32| ((size as f32 * scale).round() as u32).clamp(1, max);
+    (scaled(width), scaled(height))
+}
+
+// Frame rate used to turn a frame number into a time for headless renders,
+// unless --fixed-fps is given
+#[cfg(not(target_arch = "wasm32"))]
+const HEADLESS_FPS: f32 = 60.0;
+
+#[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+fn instance_backends(options: &Options) -> wgpu::Backends {
+    // Only WebGL2 is available in the browser, so `--backend` doesn't apply
+    #[cfg(target_arch = "wasm32")]
+    return wgpu::Backends::GL;
+    #[cfg(not(target_arch = "wasm32"))]
+    options.backends
+}
+
+fn create_instance(options: &Options) -> wgpu::Instance {
+    wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: instance_backends(options),
+        dx12_shader_compiler: Default::default(),
+    })
+}
+
+// Names of `backends` as `--backend` spells them, for error messages
+fn backend_names(backends: wgpu::Backends) -> String {
+    let names: Vec<_> = [
+        (wgpu::Backends::VULKAN, "vulkan"),
+        (wgpu::Backends::METAL, "metal"),
+        (wgpu::Backends::DX12, "dx12"),
+        (wgpu::Backends::GL, "gl"),
+    ]
+    .into_iter()
+    .filter(|(backend, _)| backends.contains(*backend))
+    .map(|(_, name)| name)
+    .collect();
+    names.join(", ")
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn list_adapters(instance: &wgpu::Instance, options: &Options) {
+    for (index, adapter) in instance.enumerate_adapters(options.backends).enumerate() {
+        let info = adapter.get_info();
+        println!(
+            "{}: {} ({:?}, {:?})",
+            index, info.name, info.backend, info.device_type
+        );
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn list_monitors(event_loop: &EventLoop<()>) {
+    for (index, monitor) in event_loop.available_monitors().enumerate() {
+        let size = monitor.size();
+        let position = monitor.position();
+        println!(
+            "{}: {} ({}x{} at {},{}, scale {})",
+            index,
+            monitor.name().unwrap_or_else(|| "unnamed".to_string()),
+            size.width,
+            size.height,
+            position.x,
+            position.y,
+            monitor.scale_factor()
+        );
+    }
+}
+
+// The monitor picked with `--monitor`. `None` stands for the primary monitor,
+// which is also used when the index is out of range.
+#[cfg(not(target_arch = "wasm32"))]
+fn select_monitor(event_loop: &EventLoop<()>, options: &Options) -> Option<MonitorHandle> {
+    let index = options.monitor?;
+    let monitor = event_loop.available_monitors().nth(index);
+    if monitor.is_none() {
+        log::warn!(
+            "No monitor with index {}, see --list-monitors; using the primary monitor",
+            index
+        );
+    }
+    monitor
+}
+
+// The `--monitor` monitor, or the primary one. Some platforms can't tell which
+// monitor is primary, in which case the first is used.
+#[cfg(not(target_arch = "wasm32"))]
+fn monitor_or_primary(
+    event_loop: &EventLoop<()>,
+    monitor: Option<MonitorHandle>,
+) -> Option<MonitorHandle> {
+    monitor
+        .or_else(|| event_loop.primary_monitor())
+        .or_else(|| event_loop.available_monitors().next())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn list_video_modes(event_loop: &EventLoop<()>, options: &Options) {
+    let Some(monitor) = monitor_or_primary(event_loop, select_monitor(event_loop, options)) else {
+        eprintln!("No monitors found");
+        return;
+    };
+    for mode in monitor.video_modes() {
+        let size = mode.size();
+        println!(
+            "{}x{}@{:.3} ({} bit)",
+            size.width,
+            size.height,
+            mode.refresh_rate_millihertz() as f32 / 1000.0,
+            mode.bit_depth()
+        );
+    }
+}
+
+// How F11 and the fullscreen flags go fullscreen. With `--exclusive` that's
+// the `--video-mode` mode, or the monitor's current resolution when there's
+// no such mode, at the highest refresh rate and bit depth available.
+// Borderless is the fallback when no video mode fits at all.
+#[cfg(not(target_arch = "wasm32"))]
+fn fullscreen_mode(
+    event_loop: &EventLoop<()>,
+    options: &Options,
+    monitor: Option<MonitorHandle>,
+) -> Fullscreen {
+    if !options.exclusive {
+        return Fullscreen::Borderless(monitor);
+    }
+    let Some(handle) = monitor_or_primary(event_loop, monitor.clone()) else {
+        log::warn!("No monitors found, using borderless fullscreen");
+        return Fullscreen::Borderless(monitor);
+    };
+
+    let best_mode = |width: u32, height: u32, refresh_rate: Option<u32>| {
+        handle
+            .video_modes()
+            .filter(|mode| mode.size() == PhysicalSize::new(width, height))
+            .filter(|mode| {
+                refresh_rate.is_none_or(|rate| {
+                    (mode.refresh_rate_millihertz() as i64 - rate as i64 * 1000).abs() < 500
+                })
+            })
+            .max_by_key(|mode| (mode.refresh_rate_millihertz(), mode.bit_depth()))
+    };
+    let requested = options.video_mode.and_then(|request| {
+        let mode = best_mode(request.width, request.height, request.refresh_rate);
+        if mode.is_none() {
+            log::warn!(
+                "No {}x{} video mode{} on this monitor, see --list-video-modes; using the current resolution",
+                request.width,
+                request.height,
+                request
+                    .refresh_rate
+                    .map(|rate| format!(" at {} Hz", rate))
+                    .unwrap_or_default()
+            );
+        }
+        mode
+    });
+    let size = handle.size();
+    match requested.or_else(|| best_mode(size.width, size.height, None)) {
+        Some(mode) => Fullscreen::Exclusive(mode),
+        None => {
+            log::warn!("No video mode matches the monitor, using borderless fullscreen");
+            Fullscreen::Borderless(monitor)
+        }
+    }
+}
+
+// What `--verbose` prints once the device exists
+fn print_device_info(adapter: &wgpu::Adapter, device: &wgpu::Device) {
+    let info = adapter.get_info();
+    println!("Adapter: {}", info.name);
+    println!("Backend: {:?}", info.backend);
+    println!("Device type: {:?}", info.device_type);
+    println!("Driver: {} {}", info.driver, info.driver_info);
+    println!("Features: {:?}", device.features());
+    println!("Limits: {:#?}", device.limits());
+}
+
+// Use the adapter picked with `--adapter` if there is one, otherwise let wgpu
+// choose, going by `--power`. If no hardware adapter fits, wgpu's fallback
+// adapter, usually a software rasterizer, is tried before giving up.
+#[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+async fn select_adapter(
+    instance: &wgpu::Instance,
+    options: &Options,
+    surface: Option<&wgpu::Surface>,
+) -> Result<wgpu::Adapter, String> {
+    // Adapters can't be enumerated in the browser
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(index) = options.adapter {
+        let adapter = instance
+            .enumerate_adapters(options.backends)
+            .nth(index)
+            .ok_or_else(|| format!("no adapter with index {}, see --list-adapters", index))?;
+        if let Some(surface) = surface {
+            if !adapter.is_surface_supported(surface) {
+                return Err(format!(
+                    "adapter {} ({}) can't present to this window",
+                    index,
+                    adapter.get_info().name
+                ));
+            }
+        }
+        return Ok(adapter);
+    }
+
+    for force_fallback_adapter in [false, true] {
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: options.power_preference,
+                compatible_surface: surface,
+                force_fallback_adapter,
+            })
+            .await;
+        if let Some(adapter) = adapter {
+            if force_fallback_adapter {
+                log::warn!(
+                    "No GPU adapter found, using the fallback adapter: {}",
+                    adapter.get_info().name
+                );
+            }
+            return Ok(adapter);
+        }
+    }
+
+    let backends = instance_backends(options);
+    let mut message = format!(
+        "no suitable GPU adapter found, tried backends: {}, with and without the fallback adapter",
+        backend_names(backends)
+    );
+    if backends != wgpu::Backends::GL {
+        message.push_str(
+            "\nOn machines without a GPU, try `--backend gl` with a software OpenGL driver such as Mesa's llvmpipe",
+        );
+    }
+    Err(message)
+}
+
+// Check the requested MSAA sample count against what the adapter supports for
+// `format`, falling back to no multisampling. Counts other than 1 and 4 are
+// only usable with adapter-specific format features, so the device features
+// to request are returned as well.
+fn select_sample_count(
+    adapter: &wgpu::Adapter,
+    format: wgpu::TextureFormat,
+    requested: u32,
+) -> (u32, wgpu::Features) {
+    if requested == 1 {
+        return (1, wgpu::Features::empty());
+    }
+
+    let features = match requested {
+        4 => wgpu::Features::empty(),
+        _ => wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
+    };
+    let supported = adapter.features().contains(features)
+        && adapter
+            .get_texture_format_features(format)
+            .flags
+            .sample_count_supported(requested);
+    if supported {
+        (requested, features)
+    } else {
+        log::warn!(
+            "{}x MSAA is not supported for {:?} on this adapter, disabling it",
+            requested,
+            format
+        );
+        (1, wgpu::Features::empty())
+    }
+}
+
+// Create a device for offscreen rendering, with no window or surface involved
+#[cfg(not(target_arch = "wasm32"))]
+fn request_headless_device(options: &Options) -> Result<(wgpu::Device, wgpu::Queue), String> {
+    let instance = create_instance(options);
+    pollster::block_on(async {
+        let adapter = select_adapter(&instance, options, None).await?;
+        request_device(&adapter, wgpu::Features::empty(), options).await
+    })
+}
+
+// Limits to request for `preset`. Downlevel and WebGL2 keep the adapter's
+// texture size limits so large windows still work.
+fn preset_limits(adapter: &wgpu::Adapter, preset: LimitsPreset) -> wgpu::Limits {
+    match preset {
+        LimitsPreset::Default => wgpu::Limits::default(),
+        LimitsPreset::Downlevel => {
+            wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits())
+        }
+        LimitsPreset::Webgl2 => {
+            wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits())
+        }
+    }
+}
+
+// Create the device with the `--limits` preset, or the most compatible one for
+// the adapter's backend. If that fails the WebGL2 limits, which nearly every
+// adapter supports, are tried before giving up.
+async fn request_device(
+    adapter: &wgpu::Adapter,
+    features: wgpu::Features,
+    options: &Options,
+) -> Result<(wgpu::Device, wgpu::Queue), String> {
+    let info = adapter.get_info();
+    log::info!("Using adapter {} ({:?})", info.name, info.backend);
+
+    // WebGL2 has no compute shaders, so compute mode needs at least the
+    // downlevel limits
+    if options.compute
+        && !adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+    {
+        return Err("the adapter doesn't support compute shaders".to_string());
+    }
+    let preset = options.limits.unwrap_or(match adapter.get_info().backend {
+        wgpu::Backend::Gl if !options.compute => LimitsPreset::Webgl2,
+        _ => LimitsPreset::Downlevel,
+    });
+
+    // Push constants replace the uniform buffer wherever the adapter can hold
+    // all of the uniforms in them. GL emulates them with plain uniforms and
+    // panics on the `u32` frame counter, so it keeps using the buffer.
+    let uniforms_size = std::mem::size_of::<Uniforms>() as u32;
+    let push_constants = adapter.features().contains(wgpu::Features::PUSH_CONSTANTS)
+        && adapter.limits().max_push_constant_size >= uniforms_size
+        && adapter.get_info().backend != wgpu::Backend::Gl;
+    let descriptor = |preset| {
+        let mut limits = preset_limits(adapter, preset);
+        // Timestamp queries are only used for profiling but cost nothing to
+        // have available
+        let mut features = features | (adapter.features() & wgpu::Features::TIMESTAMP_QUERY);
+        if push_constants {
+            features |= wgpu::Features::PUSH_CONSTANTS;
+            limits.max_push_constant_size = uniforms_size;
+        }
+        wgpu::DeviceDescriptor {
+            label: None,
+            features,
+            limits,
+        }
+    };
+
+    // Name what was asked for, since that's usually what the driver refused
+    let failed = |preset, err: wgpu::RequestDeviceError| {
+        let descriptor: wgpu::DeviceDescriptor = descriptor(preset);
+        format!(
+            "failed to create a device on {} with {:?} limits and features {:?} ({}), \
+             try another --backend or --adapter",
+            adapter.get_info().name,
+            preset,
+            descriptor.features,
+            err
+        )
+    };
+
+    let (device, queue) = match adapter.request_device(&descriptor(preset), None).await {
+        Ok(device) => device,
+        Err(err) if preset != LimitsPreset::Webgl2 && !options.compute => {
+            log::warn!(
+                "Failed to create a device with {:?} limits ({}), retrying with WebGL2 limits",
+                preset,
+                err
+            );
+            adapter
+                .request_device(&descriptor(LimitsPreset::Webgl2), None)
+                .await
+                .map_err(|err| failed(LimitsPreset::Webgl2, err))?
+        }
+        Err(err) => return Err(failed(preset, err)),
+    };
+
+    if options.verbose {
+        print_device_info(adapter, &device);
+    }
+    Ok((device, queue))
+}
+
+// Device and renderer for the modes that draw without a window or surface,
+// along with a texture to draw frames into whose pixels aren't needed
+#[cfg(not(target_arch = "wasm32"))]
+struct Offscreen {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    renderer: Renderer,
+    uniforms: Uniforms,
+    target: wgpu::TextureView,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Offscreen {
+    fn new(
+        options: &Options,
+        fragment_source: &FragmentSource,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, String> {
+        let (device, queue) = request_headless_device(options)?;
+
+        let (render_width, render_height) = render_size(&device, width, height, options.scale);
+        let mut uniforms = Uniforms::configured(render_width, render_height, options);
+        uniforms.dpi = options.scale;
+
+        let channels = Channel::load_all(&device, &queue, &options.channels, options.mipmaps)?;
+        let renderer = Renderer::new(
+            &device,
+            &queue,
+            fragment_source,
+            options
+                .format
+                .unwrap_or(wgpu::TextureFormat::Rgba8UnormSrgb),
+            1,
+            &uniforms,
+            channels,
+            options,
+        )
+        .map_err(|err| {
+            format!(
+                "failed to compile shader '{}':\n{}",
+                fragment_source.name, err
+            )
+        })?;
+
+        let target = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Offscreen Target Texture"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: renderer.format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        Ok(Self {
+            device,
+            queue,
+            renderer,
+            uniforms,
+            target,
+        })
+    }
+
+    // Upload the current uniforms and draw a frame into the target
+    fn draw(&mut self) {
+        self.renderer.update(&self.queue, &self.uniforms);
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Offscreen Encoder"),
+            });
+        self.renderer.draw(&mut encoder, &self.target);
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+}
+
+// Render frame number `frame` of `fragment_source` without a window or
+// surface and return it as an image. The same options,
+// source and frame always give the same pixels on a given adapter, which makes
+// this the thing to compare against reference images.
+#[cfg(not(target_arch = "wasm32"))]
+fn render_frame(
+    options: &Options,
+    fragment_source: &FragmentSource,
+    width: u32,
+    height: u32,
+    frame: u32,
+    time: Option<f32>,
+) -> Result<image::DynamicImage, String> {
+    let mut offscreen = Offscreen::new(options, fragment_source, width, height)?;
+    let fps = options.fixed_fps.unwrap_or(HEADLESS_FPS);
+    offscreen.uniforms.delta = 1.0 / fps;
+
+    // Play the frames leading up to the requested one so that shaders reading
+    // the previous frame see the same history they would in a window
+    for frame in 0..frame {
+        offscreen.uniforms.time = frame as f32 / fps * options.speed;
+        offscreen.uniforms.frame = frame;
+        offscreen.draw();
+    }
+
+    offscreen.uniforms.time = time.unwrap_or(frame as f32 / fps * options.speed);
+    offscreen.uniforms.frame = frame;
+    offscreen
+        .renderer
+        .update(&offscreen.queue, &offscreen.uniforms);
+    capture_image(
+        &offscreen.device,
+        &offscreen.queue,
+        &mut offscreen.renderer,
+        width,
+        height,
+        true,
+    )
+}
+
+// Render a single frame without creating a window or surface, and return its
+// number
+#[cfg(not(target_arch = "wasm32"))]
+fn render_headless(
+    options: &Options,
+    headless: &Headless,
+    fragment_source: &FragmentSource,
+) -> Result<u32, String> {
+    // A `--time` frame is numbered, and has the history leading up to it
+    // played, like the frame nearest to that time
+    let frame = match headless.time {
+        Some(time) if options.speed > 0.0 => {
+            let fps = options.fixed_fps.unwrap_or(HEADLESS_FPS);
+            (time / options.speed * fps).round() as u32
+        }
+        Some(_) => 0,
+        None => headless.frame,
+    };
+    render_frame(
+        options,
+        fragment_source,
+        headless.width,
+        headless.height,
+        frame,
+        headless.time,
+    )?
+    .save(&headless.out)
+    .map_err(|err| err.to_string())?;
+    Ok(frame)
+}
+
+// The shader `options` ask for: SHADER, wrapped for `--shadertoy`, or else the
+// built-in pattern
+#[cfg(not(target_arch = "wasm32"))]
+fn load_shader(options: &Options) -> Result<FragmentSource, String> {
+    let Some(path) = &options.shader_path else {
+        return Ok(if options.compute {
+            FragmentSource::compute()
+        } else {
+            FragmentSource::preset(0)
+        });
+    };
+    let source = FragmentSource::load(path)?;
+    let source = if options.shadertoy {
+        source.shadertoy()?
+    } else {
+        source
+    };
+    Ok(FragmentSource {
+        entry_point: options.fs_entry.clone(),
+        vertex_entry_point: options.vs_entry.clone(),
+        ..source
+    })
+}
+
+// Run the `shader` command with the process's arguments. Bad arguments come
+// back as a `UsageError`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run() -> Result<(), Box<dyn Error>> {
+    // Only this crate's messages unless RUST_LOG says otherwise, since the
+    // graphics stack warns about every probe that fails while picking an adapter
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("shader=info"))
+        .init();
+
+    let options = Options::parse(std::env::args().skip(1)).map_err(UsageError)?;
+
+    if options.help {
+        println!("{}", options::USAGE);
+        return Ok(());
+    }
+
+    if options.list_adapters {
+        list_adapters(&create_instance(&options), &options);
+        return Ok(());
+    }
+
+    if options.list_monitors {
+        list_monitors(&EventLoop::new());
+        return Ok(());
+    }
+
+    if options.list_video_modes {
+        list_video_modes(&EventLoop::new(), &options);
+        return Ok(());
+    }
+
+    // Load the fragment shader from the path given on the command line, if any
+    let fragment_source = load_shader(&options).map_err(|err| {
+        let path = options.shader_path.as_deref().unwrap_or(Path::new(""));
+        format!("Failed to read shader '{}': {}", path.display(), err)
+    })?;
+
+    if let Some(headless) = &options.headless {
+        let frame = render_headless(&options, headless, &fragment_source)
+            .map_err(|err| format!("Headless render failed: {}", err))?;
+        println!("Saved frame {} to '{}'", frame, headless.out.display());
+        return Ok(());
+    }
+
+    if let Some(bench) = &options.bench {
+        bench::bench(&options, bench, &fragment_source)
+            .map_err(|err| format!("Benchmark failed: {}", err))?;
+        return Ok(());
+    }
+
+    if let Some(recording) = &options.record {
+        record::record(&options, recording, &fragment_source)
+            .map_err(|err| format!("Recording failed: {}", err))?;
+        return Ok(());
+    }
+
+    Ok(pollster::block_on(run_window(options, fragment_source))?)
+}
+
+// There's no command line in the browser, so the defaults and the first preset
+// are used. The window opens once the page gets around to it, and any error
+// from then on goes to the console.
+#[cfg(target_arch = "wasm32")]
+pub fn run() -> Result<(), Box<dyn Error>> {
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+    console_log::init()?;
+    wasm_bindgen_futures::spawn_local(async {
+        if let Err(err) = run_window(Options::default(), FragmentSource::preset(0)).await {
+            log::error!("{}", err);
+        }
+    });
+    Ok(())
+}
+
+// The device and everything made with it to draw into the window. It's
+// created by `Gpu::new`, and again from scratch when the device is lost.
+struct Gpu {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    // Present modes the V key cycles through, vsynced first
+    present_modes: Vec<wgpu::PresentMode>,
+    renderer: Renderer,
+    overlay: Overlay,
+    panel: Panel,
+    #[cfg(not(target_arch = "wasm32"))]
+    frame_dumper: Option<dump::FrameDumper>,
+    // What the device has reported since it was created
+    #[cfg(not(target_arch = "wasm32"))]
+    errors: Arc<DeviceErrors>,
+}
+
+impl Gpu {
+    // Resize the surface to `size` and the render targets to match, which
+    // `--scale` can make a different size
+    fn resize(&mut self, size: PhysicalSize<u32>, scale: f32, uniforms: &mut Uniforms) {
+        self.config.width = size.width;
+        self.config.height = size.height;
+        self.surface.configure(&self.device, &self.config);
+        log::debug!(
+            "Resized the surface to {}x{}",
+            self.config.width,
+            self.config.height
+        );
+        let (width, height) = render_size(&self.device, size.width, size.height, scale);
+        self.renderer.resize(&self.device, width, height);
+        uniforms.set_resolution(width, height);
+    }
+
+    // Let finished readbacks complete and return the average GPU time of the
+    // shader pass, if it's measured
+    fn poll(&mut self) -> Option<f32> {
+        self.device.poll(wgpu::Maintain::Poll);
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(frame_dumper) = &mut self.frame_dumper {
+            frame_dumper.collect();
+        }
+        self.renderer.timer.as_mut().and_then(|timer| {
+            timer.collect();
+            timer.average_ms()
+        })
+    }
+
+    // Upload `uniforms` for the frames drawn from now on
+    fn update(&mut self, uniforms: &Uniforms) {
+        self.renderer.update(&self.queue, uniforms);
+    }
+
+    // Draw a frame and present it, with the overlay and the panel on top while
+    // they're shown. The panel edits `uniforms`, which `update` uploads.
+    fn render(
+        &mut self,
+        window: &Window,
+        uniforms: &mut Uniforms,
+        show_overlay: bool,
+    ) -> Result<(), wgpu::SurfaceError> {
+        let output = self.surface.get_current_texture()?;
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        self.renderer.draw(&mut encoder, &view);
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(frame_dumper) = &mut self.frame_dumper {
+            frame_dumper.capture(
+                &self.device,
+                &mut encoder,
+                &self.renderer,
+                uniforms.frame,
+                self.config.width,
+                self.config.height,
+            );
+        }
+        if show_overlay {
+            self.overlay.draw(
+                &self.queue,
+                &mut encoder,
+                &view,
+                self.config.width,
+                self.config.height,
+            );
+        }
+        if self.panel.visible {
+            self.panel.draw(
+                window,
+                &self.device,
+                &self.queue,
+                &mut encoder,
+                &view,
+                uniforms,
+            );
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+        if let Some(timer) = &mut self.renderer.timer {
+            timer.submitted();
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(frame_dumper) = &mut self.frame_dumper {
+            frame_dumper.submitted();
+        }
+        output.present();
+        Ok(())
+    }
+
+    // Connect `window` to a GPU and set up drawing `source` into it, updating
+    // `uniforms.resolution` to the render size
+    async fn new(
+        event_loop: &EventLoopWindowTarget<()>,
+        window: &Window,
+        options: &Options,
+        source: &FragmentSource,
+        uniforms: &mut Uniforms,
+    ) -> Result<Gpu, String> {
+        // Set up the GPU instance
+        let instance = create_instance(options);
+
+        // Connect to the GPU surface
+        let surface = unsafe { instance.create_surface(window) }
+            .map_err(|err| format!("failed to create a surface for the window: {}", err))?;
+        let adapter = select_adapter(&instance, options, Some(&surface))
+            .await
+            .map_err(|err| format!("failed to select a GPU adapter: {}", err))?;
+
+        // Pick the surface format. An sRGB one is preferred so the hardware does
+        // the gamma encoding, but `--colorspace` decides how colors end up either
+        // way, with the blit converting if the format doesn't match.
+        let surface_caps = surface.get_capabilities(&adapter);
+        // Broken driver and backend combinations can report a surface they can't
+        // present to at all
+        let info = adapter.get_info();
+        for (what, empty) in [
+            ("formats", surface_caps.formats.is_empty()),
+            ("alpha modes", surface_caps.alpha_modes.is_empty()),
+            ("present modes", surface_caps.present_modes.is_empty()),
+        ] {
+            if empty {
+                return Err(format!(
+                    "the window surface has no supported {} on {} ({:?}), try another \
+                     backend with --backend or another adapter from --list-adapters",
+                    what, info.name, info.backend
+                ));
+            }
+        }
+        let requested_format = options.format.filter(|format| {
+            let supported = surface_caps.formats.contains(format);
+            if !supported {
+                log::warn!(
+                    "The surface doesn't support {:?}, only {:?}, using the default",
+                    format,
+                    surface_caps.formats
+                );
+            }
+            supported
+        });
+        let surface_format = requested_format
+            .as_ref()
+            .or_else(|| surface_caps.formats.iter().find(|f| f.is_srgb()))
+            .unwrap_or(&surface_caps.formats[0]);
+
+        let (sample_count, features) = select_sample_count(
+            &adapter,
+            render_target_format(*surface_format, options.tonemap),
+            options.msaa,
+        );
+
+        // Create the device and command queue
+        let (device, queue) = request_device(&adapter, features, options).await?;
+        #[cfg(not(target_arch = "wasm32"))]
+        let errors = watch_for_errors(&device);
+
+        // Fifo should always be supported, the others only on some adapters
+        let present_modes: Vec<wgpu::PresentMode> = [
+            wgpu::PresentMode::Fifo,
+            wgpu::PresentMode::Mailbox,
+            wgpu::PresentMode::Immediate,
+        ]
+        .into_iter()
+        .filter(|mode| surface_caps.present_modes.contains(mode))
+        .collect();
+
+        // Without vsync tearing is preferred over Mailbox's dropped frames, since
+        // that's what measures the shader best
+        let preferred = match options.present_mode {
+            Some(mode) => vec![mode],
+            None if options.vsync => vec![wgpu::PresentMode::Fifo],
+            None => vec![wgpu::PresentMode::Immediate, wgpu::PresentMode::Mailbox],
+        };
+        let present_mode = match preferred.iter().find(|mode| present_modes.contains(mode)) {
+            Some(&mode) => mode,
+            None if present_modes.contains(&wgpu::PresentMode::Fifo) => {
+                match options.present_mode {
+                    Some(mode) => log::warn!("The surface doesn't support {:?}, using Fifo", mode),
+                    None => log::warn!("The surface can only present with vsync, keeping it on"),
+                }
+                wgpu::PresentMode::Fifo
+            }
+            None => {
+                let mode = surface_caps.present_modes[0];
+                log::warn!(
+                    "The surface doesn't support Fifo, presenting with {:?}",
+                    mode
+                );
+                mode
+            }
+        };
+
+        // Configure the surface
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: *surface_format,
+            width: window.inner_size().width,
+            height: window.inner_size().height,
+            present_mode,
+            alpha_mode: surface_caps.alpha_modes[0],
+            view_formats: vec![],
+        };
+        surface.configure(&device, &config);
+        log::debug!(
+            "Configured the surface: {}x{}, {:?}, {:?}",
+            config.width,
+            config.height,
+            config.format,
+            config.present_mode
+        );
+
+        if options.verbose {
+            println!("Surface format: {:?}", config.format);
+            println!("Present mode: {:?}", config.present_mode);
+        }
+
+        let channels = Channel::load_all(&device, &queue, &options.channels, options.mipmaps)?;
+
+        let (width, height) = render_size(&device, config.width, config.height, options.scale);
+        uniforms.set_resolution(width, height);
+        let mut renderer = Renderer::new(
+            &device,
+            &queue,
+            source,
+            config.format,
+            sample_count,
+            uniforms,
+            channels,
+            options,
+        )
+        .map_err(|err| format!("failed to compile shader '{}':\n{}", source.name, err))?;
+
+        // GPU time of the shader pass, shown in the overlay
+        renderer.timer = GpuTimer::new(&device, &queue);
+        if renderer.timer.is_none() && options.verbose {
+            println!("GPU timestamps aren't supported on this adapter, GPU time won't be measured");
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let frame_dumper = match &options.frame_dump {
+            Some(settings) => Some(
+                dump::FrameDumper::new(settings, config.format)
+                    .map_err(|err| format!("failed to set up --frame-dump-dir: {}", err))?,
+            ),
+            None => None,
+        };
+
+        Ok(Gpu {
+            overlay: Overlay::new(&device, config.format),
+            panel: Panel::new(event_loop, window, &device, config.format),
+            surface,
+            device,
+            queue,
+            config,
+            present_modes,
+            renderer,
+            #[cfg(not(target_arch = "wasm32"))]
+            frame_dumper,
+            #[cfg(not(target_arch = "wasm32"))]
+            errors,
+        })
+    }
+}
+
+// What wgpu reports for work on a lost device. wgpu 0.16 has no device lost
+// callback and wraps the underlying error transparently, so its message is
+// the only way to tell loss apart from other errors.
+#[cfg(not(target_arch = "wasm32"))]
+const DEVICE_LOST_MESSAGE: &str = "Parent device is lost";
+
+// How often to try recreating a lost device
+#[cfg(not(target_arch = "wasm32"))]
+const RECOVERY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+// Errors no error scope caught on a device, which wgpu would otherwise panic on
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+struct DeviceErrors {
+    lost: AtomicBool,
+    // The latest error other than device loss, until it's taken
+    last: Mutex<Option<String>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DeviceErrors {
+    // Whether the device reported that it's been lost
+    fn lost(&self) -> bool {
+        self.lost.load(Ordering::Acquire)
+    }
+
+    // The latest other error, if there's been one since the last call
+    fn take(&self) -> Option<String> {
+        self.last.lock().unwrap().take()
+    }
+}
+
+// Flag device loss and log other errors as they're reported, instead of
+// panicking on them
+#[cfg(not(target_arch = "wasm32"))]
+fn watch_for_errors(device: &wgpu::Device) -> Arc<DeviceErrors> {
+    let errors = Arc::new(DeviceErrors::default());
+    let handler_errors = errors.clone();
+    device.on_uncaptured_error(Box::new(move |err| {
+        let mut source: Option<&(dyn std::error::Error + 'static)> = Some(&err);
+        while let Some(error) = source {
+            if error.to_string() == DEVICE_LOST_MESSAGE {
+                handler_errors.lost.store(true, Ordering::Release);
+                return;
+            }
+            source = error.source();
+        }
+        log::error!("wgpu error: {}", err);
+        *handler_errors.last.lock().unwrap() = Some(err.to_string());
+    }));
+    errors
+}
+
+// Open the window and run the event loop. Setup is async so that the browser
+// can drive it; natively it's simply blocked on. Only setting up can fail,
+// since the event loop never returns.
+async fn run_window(options: Options, fragment_source: FragmentSource) -> Result<(), String> {
+    let shader_path = options.shader_path.clone();
+
+    // Set up the window
+    let event_loop = EventLoop::new();
+    let builder = WindowBuilder::new()
+        .with_title(window_title(&options, &fragment_source))
+        .with_inner_size(LogicalSize::new(1280.0, 720.0));
+    // Come back where the window was left last time, at the `--windowed` size
+    // if one was given
+    #[cfg(not(target_arch = "wasm32"))]
+    let builder = match WindowGeometry::load() {
+        Some(geometry) => geometry.restore(builder),
+        None => builder,
+    };
+    let builder = match options.windowed {
+        Some((width, height)) => builder.with_inner_size(LogicalSize::new(width, height)),
+        None => builder,
+    };
+    // F11 goes fullscreen on the `--monitor` monitor, and picking one starts
+    // out fullscreen there, as do `--fullscreen` and `--exclusive`
+    #[cfg(not(target_arch = "wasm32"))]
+    let fullscreen = fullscreen_mode(&event_loop, &options, select_monitor(&event_loop, &options));
+    #[cfg(not(target_arch = "wasm32"))]
+    let builder = if options.fullscreen || options.exclusive || options.monitor.is_some() {
+        builder.with_fullscreen(Some(fullscreen.clone()))
+    } else {
+        builder
+    };
+    let window = builder
+        .build(&event_loop)
+        .map_err(|err| format!("Failed to create the window: {}", err))?;
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut geometry = WindowGeometry::of(&window);
+
+    // In the browser the window is a canvas that has to be put on the page
+    #[cfg(target_arch = "wasm32")]
+    {
+        use winit::platform::web::WindowExtWebSys;
+        web_sys::window()
+            .and_then(|win| win.document())
+            .and_then(|doc| doc.body())
+            .and_then(|body| body.append_child(&window.canvas()).ok())
+            .expect("failed to add the canvas to the page");
+    }
+
+    let mut uniforms = Uniforms::configured(0, 0, &options);
+    uniforms.dpi = window.scale_factor() as f32 * options.scale;
+    let gpu = Gpu::new(
+        &event_loop,
+        &window,
+        &options,
+        &fragment_source,
+        &mut uniforms,
+    )
+    .await?;
+    // Start with the cursor in the middle, where the pattern is
+    uniforms.mouse = [
+        uniforms.resolution[0] / 2.0,
+        uniforms.resolution[1] / 2.0,
+        0.0,
+        0.0,
+    ];
+
+    // Shaders the arrow keys switch between: the file from the command line,
+    // if any, followed by the built-in presets. The presets are fragment
+    // shaders, so there's nothing to switch to in compute mode.
+    let mut sources = Vec::new();
+    if shader_path.is_some() || options.compute {
+        sources.push(fragment_source);
+    }
+    if !options.compute {
+        sources.extend((0..PRESETS.len()).map(FragmentSource::preset));
+    }
+    let mut current = 0;
+
+    // Watch the shader file and its includes so edits are picked up while
+    // running
+    let (reload_tx, reload_rx) = mpsc::channel::<()>();
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut watcher = match shader_path {
+        Some(_) => ShaderWatcher::new(&sources[0].files, reload_tx),
+        None => None,
+    };
+    #[cfg(target_arch = "wasm32")]
+    drop(reload_tx);
+
+    // Timer for animation
+    let mut clock = Clock::new();
+    let mut last_frame = Instant::now();
+
+    let mut cursor_inside = true;
+    let mut modifiers = ModifiersState::empty();
+    // Set while the middle button drags the view around
+    let mut panning = false;
+
+    // What C cycles through, starting from the `--palette` one if given.
+    // `None` is the built-in shader's own colors.
+    let palettes = options
+        .palette
+        .iter()
+        .cloned()
+        .chain(Palette::builtin())
+        .collect::<Vec<_>>();
+    let mut palette = options.palette.is_some().then_some(0);
+
+    // Frame timing shown in the overlay, averaged over `STATS_INTERVAL`
+    let mut show_overlay = false;
+    let mut stats = FrameStats::new();
+
+    // GPU time of the shader pass is printed every `GPU_REPORT_INTERVAL` with
+    // --verbose
+    let mut gpu_report_time = 0.0;
+
+    // Without an input device the audio levels simply stay at zero
+    #[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+    let mut audio = audio::Audio::new()
+        .map_err(|err| log::warn!("Audio input unavailable: {}", err))
+        .ok();
+
+    // Held in an option so that shutting down can drop it before the window,
+    // and so it can be rebuilt when the device is lost
+    let mut gpu = Some(gpu);
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut last_recovery: Option<Instant> = None;
+
+    // While paused frames are only drawn when something changes, and . draws
+    // one that's a step further on
+    let step = options
+        .step
+        .or(options.fixed_fps.map(|fps| 1.0 / fps))
+        .unwrap_or(DEFAULT_STEP);
+    let mut redraw = true;
+    let mut step_pending = false;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut frame_limiter = options.fps_cap.map(FrameLimiter::new);
+
+    // Run the event loop
+    event_loop.run(move |event, target, control_flow| {
+        // Only needed to recover from device loss, which isn't done in the
+        // browser
+        #[cfg(target_arch = "wasm32")]
+        let _ = target;
+
+        // Wait for submitted frames and timestamp readbacks to finish rather
+        // than tearing the device down with work in flight, then release the
+        // surface while the window it presents to still exists. Output
+        // textures are always presented within the redraw that acquired them,
+        // so none is held at this point.
+        if let Event::LoopDestroyed = event {
+            if let Some(mut gpu) = gpu.take() {
+                gpu.device.poll(wgpu::Maintain::Wait);
+                if let Some(timer) = &mut gpu.renderer.timer {
+                    timer.collect();
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some(frame_dumper) = &mut gpu.frame_dumper {
+                    frame_dumper.finish(&gpu.device);
+                }
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(geometry) = geometry {
+                geometry.save();
+            }
+            log::debug!("Shut down cleanly");
+            return;
+        }
+
+        // Nothing made from a lost device can be used again, so it's all
+        // dropped and built afresh, retrying every `RECOVERY_INTERVAL` while
+        // the driver comes back
+        #[cfg(not(target_arch = "wasm32"))]
+        if gpu
+            .as_ref()
+            .is_none_or(|gpu| gpu.errors.lost())
+        {
+            if gpu.take().is_some() {
+                log::warn!("The GPU device was lost, recreating it");
+            }
+            if last_recovery.is_none_or(|time| time.elapsed() >= RECOVERY_INTERVAL) {
+                last_recovery = Some(Instant::now());
+                let source = &sources[current];
+                match pollster::block_on(Gpu::new(target, &window, &options, source, &mut uniforms)) {
+                    Ok(new_gpu) => {
+                        log::info!("Recovered from the device loss");
+                        gpu = Some(new_gpu);
+                        redraw = true;
+                    }
+                    Err(err) => log::error!("Failed to recover from the device loss: {}", err),
+                }
+            }
+        }
+        let Some(gpu) = &mut gpu else {
+            return;
+        };
+
+        // Anything that happens to the window may change what a paused frame
+        // looks like
+        if let Event::WindowEvent { .. } = event {
+            redraw = true;
+        }
+
+        // Held keys are tracked alongside whatever else they do. Releases are
+        // missed while the window is out of focus, so losing it clears them.
+        match &event {
+            Event::WindowEvent {
+                event: WindowEvent::KeyboardInput { input, .. },
+                ..
+            } => uniforms.track_key(input),
+            Event::WindowEvent {
+                event: WindowEvent::Focused(false),
+                ..
+            } => uniforms.keys = 0,
+            _ => {}
+        }
+
+        match event {
+            // Keep typing and dragging in the panel from reaching the shortcuts
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() && gpu.panel.on_event(event) => {}
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => match event {
+                // A shader reading `keys` has them to itself, rather than also
+                // taking screenshots, switching shaders, scrubbing and panning
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            virtual_keycode: Some(key),
+                            ..
+                        },
+                    ..
+                } if gpu.renderer.reads_keys && KEYS.contains(key) => {}
+                WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                #[cfg(not(target_arch = "wasm32"))]
+                WindowEvent::Moved(_) => {
+                    geometry = WindowGeometry::of(&window).or(geometry);
+                }
+                WindowEvent::Resized(physical_size) => {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        geometry = WindowGeometry::of(&window).or(geometry);
+                    }
+                    gpu.resize(*physical_size, options.scale, &mut uniforms);
+                }
+                WindowEvent::ScaleFactorChanged {
+                    scale_factor,
+                    new_inner_size,
+                } => {
+                    log::debug!("Scale factor changed to {}", scale_factor);
+                    uniforms.dpi = *scale_factor as f32 * options.scale;
+                    gpu.resize(**new_inner_size, options.scale, &mut uniforms);
+                }
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::Space),
+                            ..
+                        },
+                    ..
+                } => clock.toggle_pause(),
+                // . pauses if needed and draws the next frame
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::Period),
+                            ..
+                        },
+                    ..
+                } => {
+                    clock.paused = true;
+                    step_pending = true;
+                }
+                // Left and Right scrub through the animation, by more with Alt.
+                // With Shift they move the center instead.
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode:
+                                Some(key @ (VirtualKeyCode::Left | VirtualKeyCode::Right)),
+                            ..
+                        },
+                    ..
+                } if !modifiers.shift() => {
+                    let step = if modifiers.alt() {
+                        SCRUB_JUMP
+                    } else {
+                        SCRUB_STEP
+                    };
+                    clock.seek(if *key == VirtualKeyCode::Left {
+                        -step
+                    } else {
+                        step
+                    });
+                    log::info!("Time: {:.2}", clock.time);
+                }
+                // Rewind the frame counter too, so shaders that initialize
+                // state on frame 0 start over
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::R),
+                            ..
+                        },
+                    ..
+                } => {
+                    clock.reset();
+                    uniforms.frame = 0;
+                    uniforms.reset_view();
+                }
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::Home),
+                            ..
+                        },
+                    ..
+                } => uniforms.reset_view(),
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode:
+                                Some(
+                                    key @ (VirtualKeyCode::LBracket
+                                    | VirtualKeyCode::RBracket
+                                    | VirtualKeyCode::Minus
+                                    | VirtualKeyCode::NumpadSubtract
+                                    | VirtualKeyCode::Equals
+                                    | VirtualKeyCode::Plus
+                                    | VirtualKeyCode::NumpadAdd),
+                                ),
+                            ..
+                        },
+                    ..
+                } => {
+                    let step = match key {
+                        VirtualKeyCode::LBracket
+                        | VirtualKeyCode::Minus
+                        | VirtualKeyCode::NumpadSubtract => -TIME_SCALE_STEP,
+                        _ => TIME_SCALE_STEP,
+                    };
+                    clock.adjust_scale(step);
+                    log::info!("Time scale: {:.2}", clock.scale);
+                }
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::F),
+                            ..
+                        },
+                    ..
+                } => show_overlay = !show_overlay,
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::Tab),
+                            ..
+                        },
+                    ..
+                } => gpu.panel.visible = !gpu.panel.visible,
+                // The surface and resolution uniform follow in the `Resized`
+                // event the switch produces
+                #[cfg(not(target_arch = "wasm32"))]
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::F11),
+                            ..
+                        },
+                    ..
+                } => match window.fullscreen() {
+                    Some(_) => window.set_fullscreen(None),
+                    None => window.set_fullscreen(Some(fullscreen.clone())),
+                },
+                // Shift+WASD or Shift+arrows nudge the center, ahead of the
+                // arrow and S bindings below
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode:
+                                Some(
+                                    key @ (VirtualKeyCode::W
+                                    | VirtualKeyCode::A
+                                    | VirtualKeyCode::S
+                                    | VirtualKeyCode::D
+                                    | VirtualKeyCode::Up
+                                    | VirtualKeyCode::Left
+                                    | VirtualKeyCode::Down
+                                    | VirtualKeyCode::Right),
+                                ),
+                            ..
+                        },
+                    ..
+                } if modifiers.shift() => {
+                    let (x, y) = match key {
+                        VirtualKeyCode::W | VirtualKeyCode::Up => (0.0, -CENTER_STEP),
+                        VirtualKeyCode::A | VirtualKeyCode::Left => (-CENTER_STEP, 0.0),
+                        VirtualKeyCode::S | VirtualKeyCode::Down => (0.0, CENTER_STEP),
+                        _ => (CENTER_STEP, 0.0),
+                    };
+                    uniforms.set_center([uniforms.center[0] + x, uniforms.center[1] + y]);
+                }
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(key @ (VirtualKeyCode::Up | VirtualKeyCode::Down)),
+                            ..
+                        },
+                    ..
+                } => {
+                    let next = if *key == VirtualKeyCode::Down {
+                        (current + 1) % sources.len()
+                    } else {
+                        (current + sources.len() - 1) % sources.len()
+                    };
+                    let reloaded = gpu.renderer.reload(&gpu.device, &mut sources[next]);
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if let (Some(watcher), Some(_)) = (&mut watcher, &sources[next].path) {
+                        watcher.watch(&sources[next].files);
+                    }
+                    if reloaded {
+                        current = next;
+                        window.set_title(&window_title(&options, &sources[current]));
+                    }
+                }
+                // Holding Ctrl pins the center to the cursor
+                WindowEvent::ModifiersChanged(state) => {
+                    modifiers = *state;
+                    if modifiers.ctrl() {
+                        uniforms.center_on_cursor();
+                    }
+                }
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::G),
+                            ..
+                        },
+                    ..
+                } => {
+                    let step = if modifiers.shift() {
+                        -GRAIN_STEP
+                    } else {
+                        GRAIN_STEP
+                    };
+                    uniforms.grain_intensity = (uniforms.grain_intensity + step).clamp(0.0, 1.0);
+                    log::info!("Grain intensity: {:.2}", uniforms.grain_intensity);
+                }
+                // E brightens the frame by a quarter stop, Shift+E darkens it
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::E),
+                            ..
+                        },
+                    ..
+                } => {
+                    let step = if modifiers.shift() {
+                        -EXPOSURE_STEP
+                    } else {
+                        EXPOSURE_STEP
+                    };
+                    uniforms.exposure += step;
+                    log::info!("Exposure: {:+.2} EV", uniforms.exposure);
+                }
+                // P prints what the shader is currently being fed
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::P),
+                            ..
+                        },
+                    ..
+                } => {
+                    let source = &sources[current];
+                    match &source.path {
+                        Some(path) => println!("shader={}", path.display()),
+                        None => println!("shader=preset:{}", source.name),
+                    }
+                    println!("{}", uniforms.describe());
+                }
+                // C switches to the next palette, Shift+C the previous one
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::C),
+                            ..
+                        },
+                    ..
+                } => {
+                    let last = palettes.len() - 1;
+                    palette = match (palette, modifiers.shift()) {
+                        (None, false) => Some(0),
+                        (None, true) => Some(last),
+                        (Some(i), false) => (i < last).then_some(i + 1),
+                        (Some(i), true) => i.checked_sub(1),
+                    };
+                    let current = palette.map(|i| &palettes[i]);
+                    uniforms.set_palette(current);
+                    log::info!(
+                        "Palette: {}",
+                        current.map_or("none", |palette| palette.name.as_str())
+                    );
+                }
+                // N picks the next grain seed, Shift+N the previous one
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::N),
+                            ..
+                        },
+                    ..
+                } => {
+                    uniforms.seed = if modifiers.shift() {
+                        (uniforms.seed - 1.0).max(0.0)
+                    } else {
+                        uniforms.seed + 1.0
+                    };
+                    log::info!("Seed: {}", uniforms.seed);
+                }
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::V),
+                            ..
+                        },
+                    ..
+                } => {
+                    let current = gpu
+                        .present_modes
+                        .iter()
+                        .position(|&mode| mode == gpu.config.present_mode);
+                    let next = current.map_or(0, |i| (i + 1) % gpu.present_modes.len());
+                    if let Some(&mode) = gpu.present_modes.get(next) {
+                        if mode != gpu.config.present_mode {
+                            gpu.config.present_mode = mode;
+                            gpu.surface.configure(&gpu.device, &gpu.config);
+                        }
+                        log::info!("Present mode: {:?}", mode);
+                    }
+                }
+                // Reading the frame back blocks, which the browser can't do
+                #[cfg(not(target_arch = "wasm32"))]
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::S),
+                            ..
+                        },
+                    ..
+                } => {
+                    let path = screenshot_path();
+                    match save_png(
+                        &gpu.device,
+                        &gpu.queue,
+                        &mut gpu.renderer,
+                        gpu.config.width,
+                        gpu.config.height,
+                        &path,
+                    ) {
+                        Ok(()) => log::info!("Saved screenshot to '{}'", path.display()),
+                        Err(err) => log::error!("Failed to save screenshot: {}", err),
+                    }
+                }
+                // X writes out the current shader, see --dump-wgsl
+                #[cfg(not(target_arch = "wasm32"))]
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::X),
+                            ..
+                        },
+                    ..
+                } => {
+                    let path = options
+                        .dump_wgsl
+                        .as_deref()
+                        .unwrap_or(Path::new("shader.wgsl"));
+                    match sources[current].reread() {
+                        Ok(source) => dump_wgsl(&source, options.compute, path),
+                        Err(err) => {
+                            log::error!("Failed to read shader '{}': {}", sources[current].name, err)
+                        }
+                    }
+                }
+                WindowEvent::CursorEntered { .. } => cursor_inside = true,
+                // Freeze the last known position while the cursor is outside,
+                // even if the platform keeps reporting moves during a drag
+                WindowEvent::CursorLeft { .. } => cursor_inside = false,
+                // The cursor is tracked in render pixels, which differ from
+                // window pixels with --scale
+                WindowEvent::CursorMoved { position, .. } if cursor_inside => {
+                    let previous = [uniforms.mouse[0], uniforms.mouse[1]];
+                    uniforms.mouse[0] =
+                        position.x as f32 * uniforms.resolution[0] / gpu.config.width as f32;
+                    uniforms.mouse[1] =
+                        position.y as f32 * uniforms.resolution[1] / gpu.config.height as f32;
+                    if modifiers.ctrl() {
+                        uniforms.center_on_cursor();
+                    }
+                    if panning {
+                        uniforms.pan(previous, [uniforms.mouse[0], uniforms.mouse[1]]);
+                    }
+                }
+                // The wheel zooms in on the cursor and middle-dragging pans
+                WindowEvent::MouseWheel { delta, .. } => {
+                    let lines = match delta {
+                        MouseScrollDelta::LineDelta(_, y) => *y,
+                        MouseScrollDelta::PixelDelta(position) => {
+                            position.y as f32 / PIXELS_PER_LINE
+                        }
+                    };
+                    uniforms.zoom_at(
+                        ZOOM_STEP.powf(lines),
+                        [uniforms.mouse[0], uniforms.mouse[1]],
+                    );
+                }
+                WindowEvent::MouseInput {
+                    state,
+                    button: MouseButton::Middle,
+                    ..
+                } => panning = *state == ElementState::Pressed,
+                WindowEvent::MouseInput {
+                    state,
+                    button: MouseButton::Left,
+                    ..
+                } => match state {
+                    ElementState::Pressed => {
+                        uniforms.mouse[2] = uniforms.mouse[0];
+                        uniforms.mouse[3] = uniforms.mouse[1];
+                    }
+                    ElementState::Released => {
+                        uniforms.mouse[2] = -uniforms.mouse[2].abs();
+                        uniforms.mouse[3] = -uniforms.mouse[3].abs();
+                    }
+                },
+                _ => {}
+            },
+            Event::RedrawRequested(window_id) if window_id == window.id() => {
+                let now = Instant::now();
+                let frame_delta = (now - last_frame).as_secs_f32();
+                last_frame = now;
+
+                // With --fixed-fps every frame advances the same amount, so the
+                // animation no longer depends on how fast frames are drawn
+                if step_pending {
+                    step_pending = false;
+                    uniforms.delta = step;
+                    uniforms.time = clock.step(step) * options.speed;
+                } else {
+                    uniforms.delta = match options.fixed_fps {
+                        Some(fps) => 1.0 / fps,
+                        None => frame_delta,
+                    };
+                    uniforms.time = clock.advance(uniforms.delta) * options.speed;
+                }
+                #[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+                if let Some(audio) = &mut audio {
+                    uniforms.audio = audio.levels();
+                }
+
+                // Readbacks complete in the background as the device is polled
+                let gpu_time = gpu.poll();
+
+                if let Some(frame_time) = stats.update(frame_delta) {
+                    let mut text = format!(
+                        "{:.0} FPS {:.2} MS",
+                        1.0 / frame_time,
+                        frame_time * 1000.0
+                    );
+                    if let Some(gpu_time) = gpu_time {
+                        text.push_str(&format!(" GPU {:.2} MS", gpu_time));
+                    }
+                    gpu.overlay.set_text(&text);
+                    if options.title_stats {
+                        window.set_title(&format!(
+                            "{} - {:.0} FPS, {:.2} ms",
+                            window_title(&options, &sources[current]),
+                            1.0 / frame_time,
+                            frame_time * 1000.0
+                        ));
+                    }
+                }
+
+                gpu_report_time += frame_delta;
+                if gpu_report_time >= GPU_REPORT_INTERVAL {
+                    if let Some(gpu_time) = gpu_time.filter(|_| options.verbose) {
+                        println!("GPU time: {:.3} ms", gpu_time);
+                    }
+                    gpu_report_time = 0.0;
+                }
+                // Fifo already waits for the display
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some(limiter) = &mut frame_limiter {
+                    if gpu.config.present_mode != wgpu::PresentMode::Fifo {
+                        limiter.wait();
+                    }
+                }
+                gpu.update(&uniforms);
+                match gpu.render(&window, &mut uniforms, show_overlay) {
+                    Ok(()) => uniforms.frame = uniforms.frame.wrapping_add(1),
+                    // The surface needs to be reconfigured, try again next
+                    // frame. Outdated surfaces are routine while resizing.
+                    Err(wgpu::SurfaceError::Outdated) => {
+                        log::debug!("Surface is outdated, reconfiguring it");
+                        gpu.surface.configure(&gpu.device, &gpu.config);
+                    }
+                    Err(wgpu::SurfaceError::Lost) => {
+                        log::warn!("Surface was lost, reconfiguring it");
+                        gpu.surface.configure(&gpu.device, &gpu.config);
+                    }
+                    Err(wgpu::SurfaceError::OutOfMemory) => {
+                        log::error!("Out of GPU memory, exiting");
+                        *control_flow = ControlFlow::Exit;
+                    }
+                    Err(wgpu::SurfaceError::Timeout) => {
+                        log::warn!("Timed out waiting for the next frame");
+                    }
+                }
+            }
+            Event::MainEventsCleared => {
+                // Drain pending change notifications so a burst of writes
+                // triggers a single rebuild
+                let changed = reload_rx.try_iter().count() > 0;
+                if changed {
+                    log::debug!("Shader file changed");
+                }
+                if changed && sources[current].path.is_some() {
+                    let reloaded = gpu.renderer.reload(&gpu.device, &mut sources[current]);
+                    if reloaded {
+                        log::info!("Reloaded shader '{}'", sources[current].name);
+                        redraw = true;
+                    }
+                    // An edit can add or drop includes
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if let Some(watcher) = &mut watcher {
+                        watcher.watch(&sources[current].files);
+                    }
+                }
+                if redraw || !clock.paused {
+                    window.request_redraw();
+                    redraw = false;
+                    *control_flow = ControlFlow::Poll;
+                } else {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        *control_flow =
+                            ControlFlow::WaitUntil(Instant::now() + PAUSED_POLL_INTERVAL);
+                    }
+                    // There's nothing to check for in the browser
+                    #[cfg(target_arch = "wasm32")]
+                    {
+                        *control_flow = ControlFlow::Wait;
+                    }
+                }
+            }
+            _ => {}
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Small enough for a software rasterizer to get through the frames leading
+    // up to the later snapshots quickly
+    const SNAPSHOT_SIZE: (u32, u32) = (64, 36);
+
+    // Largest mean difference per channel, out of 255, a frame can have from
+    // its snapshot. The grain hashes `sin`, whose precision varies by GPU.
+    const SNAPSHOT_TOLERANCE: f64 = 4.0;
+
+    fn has_adapter(options: &Options) -> bool {
+        let instance = create_instance(options);
+        pollster::block_on(select_adapter(&instance, options, None)).is_ok()
+    }
+
+    // Render the built-in shader at `time` and compare it with its snapshot in
+    // tests/snapshots, or write the snapshot when SHADER_UPDATE_SNAPSHOTS is set.
+    // Machines without any adapter skip the comparison.
+    fn check_snapshot(time: f32) {
+        let options = Options::default();
+        if !has_adapter(&options) {
+            eprintln!("No adapter, skipping the snapshot at {}s", time);
+            return;
+        }
+
+        let (width, height) = SNAPSHOT_SIZE;
+        let frame = (time * HEADLESS_FPS).round() as u32;
+        let source = FragmentSource::preset(0);
+        let image = render_frame(&options, &source, width, height, frame, Some(time))
+            .unwrap()
+            .to_rgba8();
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/snapshots")
+            .join(format!("psychedelic-{:.1}s.png", time));
+        if std::env::var_os("SHADER_UPDATE_SNAPSHOTS").is_some() {
+            image.save(&path).unwrap();
+            return;
+        }
+
+        let snapshot = image::open(&path)
+            .unwrap_or_else(|err| panic!("failed to open '{}': {}", path.display(), err))
+            .to_rgba8();
+        assert_eq!(image.dimensions(), snapshot.dimensions());
+        let difference = image
+            .as_raw()
+            .iter()
+            .zip(snapshot.as_raw())
+            .map(|(&a, &b)| (a as f64 - b as f64).abs())
+            .sum::<f64>()
+            / image.as_raw().len() as f64;
+        assert!(
+            difference <= SNAPSHOT_TOLERANCE,
+            "the frame at {}s differs from '{}' by {:.2} on average",
+            time,
+            path.display(),
+            difference
+        );
+    }
+
+    #[test]
+    fn snapshot_at_start() {
+        check_snapshot(0.0);
+    }
+
+    #[test]
+    fn snapshot_at_1_5s() {
+        check_snapshot(1.5);
+    }
+
+    #[test]
+    fn snapshot_at_3s() {
+        check_snapshot(3.0);
+    }
+
+    #[test]
+    fn loads_and_validates_shader_file() {
+        let path = std::env::temp_dir().join(format!("shader-test-{}.wgsl", std::process::id()));
+        std::fs::write(
+            &path,
+            "@fragment\nfn fs_main(@location(0) color: vec4<f32>) -> @location(0) vec4<f32> {\n    return color;\n}\n",
+        )
+        .unwrap();
+        let source = FragmentSource::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let source = source.unwrap();
+        assert!(source.language == ShaderLanguage::Wgsl);
+        let module = naga::front::wgsl::parse_str(&source.code).unwrap();
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .unwrap();
+    }
+
+    #[test]
+    fn load_reports_missing_file() {
+        assert!(FragmentSource::load(Path::new("does-not-exist.wgsl")).is_err());
+    }
+
+    #[test]
+    fn watches_included_files() {
+        let dir = std::env::temp_dir().join(format!("shader-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let main = dir.join("main.wgsl");
+        let common = dir.join("common.wgsl");
+        std::fs::write(&main, "#include \"common.wgsl\"\n").unwrap();
+        std::fs::write(&common, "const RED = 1.0;\n").unwrap();
+
+        let source = FragmentSource::load(&main).unwrap();
+        assert_eq!(source.files, [main.clone(), common.clone()]);
+        let (reload_tx, reload_rx) = mpsc::channel();
+        let watcher = ShaderWatcher::new(&source.files, reload_tx).unwrap();
+        std::fs::write(&common, "const RED = 0.5;\n").unwrap();
+        let reloaded = reload_rx.recv_timeout(std::time::Duration::from_secs(5));
+        drop(watcher);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(
+            reloaded.is_ok(),
+            "editing an include didn't trigger a reload"
+        );
+    }
+
+    #[test]
+    fn resize_scales_mouse() {
+        let mut uniforms = Uniforms::new(200, 100);
+        uniforms.mouse = [50.0, 25.0, -100.0, 75.0];
+        uniforms.set_resolution(400, 50);
+        assert_eq!(uniforms.mouse, [100.0, 12.5, -200.0, 37.5]);
+        assert_eq!(uniforms.resolution, [400.0, 50.0]);
+
+        // Nothing to scale from
+        let mut uniforms = Uniforms::new(0, 0);
+        uniforms.mouse = [10.0, 20.0, 30.0, 40.0];
+        uniforms.set_resolution(400, 50);
+        assert_eq!(uniforms.mouse, [10.0, 20.0, 30.0, 40.0]);
+    }
+
+    #[test]
+    fn screenshot_keeps_feedback_target() {
+        let options = Options::default();
+        if !has_adapter(&options) {
+            eprintln!("No adapter, skipping the screenshot test");
+            return;
+        }
+
+        let mut offscreen = Offscreen::new(&options, &FragmentSource::preset(0), 16, 16).unwrap();
+        offscreen.uniforms.time = 1.0;
+        offscreen.draw();
+        let target = offscreen.renderer.target;
+        let path =
+            std::env::temp_dir().join(format!("shader-screenshot-{}.png", std::process::id()));
+        let Offscreen {
+            device,
+            queue,
+            renderer,
+            ..
+        } = &mut offscreen;
+        save_png(device, queue, renderer, 16, 16, &path).unwrap();
+        let screenshot = image::open(&path).unwrap().to_rgba8();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(renderer.target, target);
+        drop(offscreen);
+
+        // The screenshot is the frame that was drawn
+        let mut expected = Offscreen::new(&options, &FragmentSource::preset(0), 16, 16).unwrap();
+        expected.uniforms.time = 1.0;
+        expected
+            .renderer
+            .update(&expected.queue, &expected.uniforms);
+        let frame = capture_image(
+            &expected.device,
+            &expected.queue,
+            &mut expected.renderer,
+            16,
+            16,
+            true,
+        )
+        .unwrap()
+        .to_rgba8();
+        assert_eq!(screenshot, frame);
+    }
+
+    // Where a `Uniforms` field named as in WGSL sits in the Rust struct
+    fn uniforms_offset(name: &str) -> usize {
+        match name {
+            "time" => std::mem::offset_of!(Uniforms, time),
+            "delta" => std::mem::offset_of!(Uniforms, delta),
+            "resolution" => std::mem::offset_of!(Uniforms, resolution),
+            "mouse" => std::mem::offset_of!(Uniforms, mouse),
+            "frame" => std::mem::offset_of!(Uniforms, frame),
+            "grain_intensity" => std::mem::offset_of!(Uniforms, grain_intensity),
+            "pulse_speed" => std::mem::offset_of!(Uniforms, pulse_speed),
+            "dpi" => std::mem::offset_of!(Uniforms, dpi),
+            "frequencies" => std::mem::offset_of!(Uniforms, frequencies),
+            "audio" => std::mem::offset_of!(Uniforms, audio),
+            "seed" => std::mem::offset_of!(Uniforms, seed),
+            "grain" => std::mem::offset_of!(Uniforms, grain),
+            "center" => std::mem::offset_of!(Uniforms, center),
+            "exposure" => std::mem::offset_of!(Uniforms, exposure),
+            "keys" => std::mem::offset_of!(Uniforms, keys),
+            "resolution_px" => std::mem::offset_of!(Uniforms, resolution_px),
+            "palette_size" => std::mem::offset_of!(Uniforms, palette_size),
+            "zoom" => std::mem::offset_of!(Uniforms, zoom),
+            "offset" => std::mem::offset_of!(Uniforms, offset),
+            "palette" => std::mem::offset_of!(Uniforms, palette),
+            _ => panic!("no `{}` field in the Rust Uniforms", name),
+        }
+    }
+
+    // The `Uniforms` struct in `code`, with its members and size
+    fn wgsl_uniforms(code: &str) -> (Vec<naga::StructMember>, u32) {
+        let module = naga::front::wgsl::parse_str(code).unwrap();
+        let uniforms = module
+            .types
+            .iter()
+            .find_map(|(_, ty)| match &ty.inner {
+                naga::TypeInner::Struct { members, span }
+                    if ty.name.as_deref() == Some("Uniforms") =>
+                {
+                    Some((members.clone(), *span))
+                }
+                _ => None,
+            })
+            .expect("the shader declares Uniforms");
+        uniforms
+    }
+
+    #[test]
+    fn uniforms_layout_matches_wgsl() {
+        let (members, span) = wgsl_uniforms(FRAGMENT_SHADER);
+        assert_eq!(span as usize, std::mem::size_of::<Uniforms>());
+        assert_eq!(members.len(), 20, "every Rust field is declared in WGSL");
+        for member in members {
+            let name = member.name.unwrap();
+            assert_eq!(
+                member.offset as usize,
+                uniforms_offset(&name),
+                "offset of `{}`",
+                name
+            );
+        }
+    }
+
+    // Presets may declare only the first fields they use, but those have to
+    // line up too
+    #[test]
+    fn preset_uniforms_match() {
+        for (name, code) in PRESETS {
+            let (members, span) = wgsl_uniforms(code);
+            assert!(span as usize <= std::mem::size_of::<Uniforms>(), "{}", name);
+            for member in members {
+                let field = member.name.unwrap();
+                assert_eq!(
+                    member.offset as usize,
+                    uniforms_offset(&field),
+                    "offset of `{}` in {}",
+                    field,
+                    name
+                );
+            }
+        }
+    }
+
+    // A fragment shader declaring the uniforms as the built-in one does, with
+    // `declaration` for the variable and `body` for `fs_main`
+    fn keys_shader(declaration: &str, body: &str) -> naga::Module {
+        let start = FRAGMENT_SHADER.find("struct Uniforms {").unwrap();
+        let end = start + FRAGMENT_SHADER[start..].find("};").unwrap() + 2;
+        let code = format!(
+            "{}\n{}\n@fragment\nfn fs_main() -> @location(0) vec4<f32> {{\n{}\n}}\n",
+            &FRAGMENT_SHADER[start..end],
+            declaration,
+            body
+        );
+        naga::front::wgsl::parse_str(&code).unwrap()
+    }
+
+    #[test]
+    fn detects_reading_keys() {
+        let uniform = "@group(0) @binding(0) var<uniform> u: Uniforms;";
+        assert!(bindings::reads_keys(&keys_shader(
+            uniform,
+            "return vec4<f32>(f32(u.keys & 1u));"
+        )));
+        assert!(bindings::reads_keys(&keys_shader(
+            uniform,
+            "let copy = u;\nreturn vec4<f32>(f32(copy.keys));"
+        )));
+        assert!(bindings::reads_keys(&keys_shader(
+            "var<push_constant> u: Uniforms;",
+            "return vec4<f32>(f32(u.keys));"
+        )));
+        assert!(!bindings::reads_keys(&keys_shader(
+            uniform,
+            "return vec4<f32>(u.time);"
+        )));
+
+        // The built-in shaders leave the keys to their shortcuts
+        let module = naga::front::wgsl::parse_str(FRAGMENT_SHADER).unwrap();
+        assert!(!bindings::reads_keys(&module));
+        for (name, code) in PRESETS {
+            let module = naga::front::wgsl::parse_str(code).unwrap();
+            assert!(!bindings::reads_keys(&module), "{}", name);
+        }
+    }
+
+    #[test]
+    fn uncaptured_errors_are_kept() {
+        let options = Options::default();
+        if !has_adapter(&options) {
+            eprintln!("No adapter, skipping the uncaptured error test");
+            return;
+        }
+
+        let (device, _queue) = request_headless_device(&options).unwrap();
+        let errors = watch_for_errors(&device);
+        // Mapping a buffer for both reading and writing is invalid
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::MAP_WRITE,
+            mapped_at_creation: false,
+        });
+        device.poll(wgpu::Maintain::Wait);
+        assert!(errors.take().is_some());
+        assert!(errors.take().is_none());
+        assert!(!errors.lost());
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        // 2024-02-29, the leap day, and the day after it
+        assert_eq!(civil_from_days(19782), (2024, 2, 29));
+        assert_eq!(civil_from_days(19783), (2024, 3, 1));
+        // Either side of the new year
+        assert_eq!(civil_from_days(19722), (2023, 12, 31));
+        assert_eq!(civil_from_days(19723), (2024, 1, 1));
+    }
+
+    #[test]
+    fn frame_stats_average_each_interval() {
+        let mut stats = FrameStats::new();
+        assert_eq!(stats.update(0.125), None);
+        assert_eq!(stats.update(0.375), Some(0.25));
+        assert_eq!(stats.update(0.25), None);
+        assert_eq!(stats.update(0.25), Some(0.25));
+    }
+}
@@ -0,0 +1,1033 @@
+// Only the defaults are used in the browser, where there's no command line
+#![cfg_attr(target_arch = "wasm32", allow(dead_code))]
+
+use crate::channel::{ChannelSpec, MAX_CHANNELS};
+use crate::config::Config;
+use crate::palette::Palette;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: shader [OPTIONS] [SHADER]
+
+SHADER is a WGSL fragment shader, or GLSL if it ends in .glsl or .frag. Lines of
+the form #include \"FILE\" are replaced by FILE, relative to the including file.
+With --compute it's a compute shader instead, with the entry point cs_main in
+WGSL or main in GLSL, writing to an rgba8unorm storage texture at binding 5.
+The fragment entry point is fs_main in WGSL or main in GLSL, unless --fs-entry
+says otherwise.
+
+Shaders declare what they read in group 0: the uniforms at binding 0, the
+previous frame as a texture_2d<f32> at 1 with its sampler at 2, channel0 at 3,
+channel1 to channel3 at 6 to 8, the sampler the channels share at 4 and the
+blue noise texture at 9. The previous frame is all zeros at first and after
+each resize, so shaders building on it can set themselves up where the
+uniforms' frame is 0.
+
+Options:
+  --config PATH            Read settings from a TOML file with any of the keys
+                           shader_path (relative to the file), fullscreen, width,
+                           height, present_mode and msaa. Flags override them.
+  --fs-entry NAME          Fragment entry point of SHADER
+  --vs-entry NAME          Take the vertex stage from entry point NAME of a WGSL SHADER
+                           instead of the built-in one. It's drawn with 3 vertices,
+                           or 6 with --fullscreen-geometry quad, and no bindings.
+  --cull FACE              Which triangles of the shader pass are culled: none, back
+                           or front (default: back)
+  --front-face WINDING     Which winding faces forward, ccw or cw (default: ccw)
+  --depth                  Give the shader pass a depth buffer, cleared to 1 each frame
+                           and tested less-or-equal, for shaders that write
+                           @builtin(frag_depth) like the Raymarch preset
+  --dump-wgsl PATH         Write each shader to PATH as it's compiled: the WGSL wgpu
+                           gets, with #includes expanded, or what naga translates a
+                           GLSL shader to. X writes the current one while running,
+                           to PATH or shader.wgsl.
+  --shadertoy              SHADER is WGSL written like a Shadertoy shader, with a
+                           fn mainImage(fragColor: ptr<function, vec4<f32>>,
+                           fragCoord: vec2<f32>) that's given pixel coordinates from
+                           the bottom-left. It can read iResolution (z is 1), iTime,
+                           iTimeDelta, iFrame and iMouse, whose xy is the cursor and
+                           zw the last click, from the bottom-left and negated once
+                           the button is released. Channels bind as usual.
+  --headless [WIDTHxHEIGHT]
+                           Render a single frame offscreen instead of opening a window,
+                           at this size or --size (default: 1280x720)
+  --frame N                Frame to render in headless mode (default: 0)
+  --time SECONDS           Render the headless frame at this animation time instead,
+                           after the frames that lead up to it
+  --out PATH               Where to write the headless frame (default: frame.png)
+  --record PATH            Record a clip offscreen: a video via ffmpeg if PATH ends in
+                           .mp4, .mkv, .mov or .webm, otherwise a directory of PNGs
+  --duration SECONDS       Length of the recording (default: 10)
+  --fps N                  Frame rate of the recording (default: 60)
+  --size WIDTHxHEIGHT      Resolution of the headless frame, recording or benchmark
+                           (default: 1280x720)
+  --bench                  Render frames offscreen as fast as possible and print
+                           frame time statistics
+  --frames N               Number of frames to benchmark (default: 1000), or to record
+                           instead of giving a --duration
+  --frame-dump-dir DIR     While running in a window, also save frames to
+                           numbered PNGs in DIR
+  --every N                Save every Nth frame with --frame-dump-dir (default: 1)
+  --vsync on|off           Whether frames wait for the display, V cycles through the
+                           present modes while running (default: on)
+  --present-mode MODE      Present with fifo, mailbox or immediate exactly, instead of
+                           leaving the choice to --vsync. Falls back to fifo if the
+                           surface doesn't support it.
+  --fps-cap FPS            Hold frames to at most FPS while vsync is off
+  --fixed-fps FPS          Advance time by 1/FPS per frame instead of following the
+                           wall clock; also sets the headless frame rate (default: 60)
+  --speed FACTOR           Multiply the time the shader sees by FACTOR, in every mode,
+                           on top of the speed keys (default: 1)
+  --step SECONDS           How far . steps the paused animation, before --speed and
+                           the speed keys (default: one --fixed-fps frame, or 1/60)
+  --channel0 INPUT         What the shader samples as `channel0`: an image file,
+                           `prev` for the previous frame or `noise` for a tiling
+                           white noise texture; likewise --channel1 to --channel3
+  --texture PATH           Same as --channel0 PATH
+  --mipmaps                Generate mipmaps for the channel images
+  --scale FACTOR           Render at FACTOR times the output resolution and filter
+                           the result down, or up below 1 (default: 1). Above 1
+                           this supersamples: each output pixel box filters the
+                           rendered pixels it covers, which smooths thin detail
+  --ssaa FACTOR            Supersample with --scale FACTOR, where FACTOR is 2 or 4
+  --clear-color R,G,B      Color the shader pass is cleared to first, each component
+                           between 0 and 1 (default: 0.1,0.2,0.3)
+  --colorspace SPACE       How shader colors are shown: srgb treats them as linear
+                           and gamma encodes them for the display, linear shows
+                           them unchanged (default: srgb)
+  --format FORMAT          Texture format to draw in: rgba8unorm, bgra8unorm, their
+                           -srgb variants, or rgba16float to let colors go above 1
+                           on HDR displays, where screenshots and headless frames
+                           are saved as 16-bit PNGs, clipped to 1. Falls back to the
+                           default if the surface doesn't support it.
+  --tonemap OPERATOR       How the final pass maps colors above 1 into the display's
+                           range: none, reinhard, aces or filmic. Anything but none
+                           renders into a float texture first (default: none)
+  --exposure EV            Stops to brighten the frame by before tone mapping, also
+                           changed with E and Shift+E (default: 0)
+  --post EFFECTS           Comma-separated effects to run on each frame, in order, before
+                           it's shown: vignette, scanlines, chromatic-aberration
+  --no-clear               Keep the previous contents instead of clearing, so any
+                           pixel the shader misses shows up as stale
+  --grain KIND             Where the built-in shader's grain comes from: value for
+                           value noise or blue for a blue noise texture, which
+                           dithers more evenly (default: value)
+  --seed N                 Grain pattern to start with; N and Shift+N step through
+                           them while running (default: 0)
+  --palette PALETTE        Recolor the built-in shader with 2 to 8 hex colors, given
+                           as a comma-separated list or a file of them. C and Shift+C
+                           cycle through it, the built-in palettes and the original
+                           colors while running.
+  --compute                Fill each frame with a compute shader instead of a
+                           fragment shader
+  --fullscreen-geometry G  How passes cover the screen: triangle for one oversized
+                           triangle or quad for two within clip space, for drivers
+                           that mishandle the former (default: triangle)
+  --msaa SAMPLES           Multisample anti-aliasing: 1, 2, 4 or 8 (default: 1)
+  --backend NAME           Restrict wgpu to one backend: vulkan, dx12, metal or gl
+  --adapter INDEX          Use the adapter with this index from --list-adapters
+  --power low|high         Prefer the integrated or the discrete GPU when picking an
+                           adapter (default: low)
+  --limits PRESET          Device limits to request: default, downlevel or webgl2
+                           (default: webgl2 on GL without --compute, downlevel
+                           elsewhere)
+  --list-adapters          Print the available adapters and exit
+  --fullscreen             Start out borderless fullscreen; F11 toggles it
+  --windowed WIDTHxHEIGHT  Start out windowed at this size in logical pixels
+                           (default: the last size used, or 1280x720)
+  --title TEXT             Window title (default: names the current shader)
+  --title-stats            Add the frame rate and frame time to the window title, as
+                           the F overlay shows them
+  --exclusive              Start out in exclusive fullscreen, which skips the
+                           compositor; F11 toggles it
+  --video-mode WxH[@HZ]    Video mode for --exclusive (default: the monitor's
+                           current resolution at its highest refresh rate)
+  --list-video-modes       Print the video modes of the --monitor monitor and exit
+  --monitor INDEX          Go fullscreen on the monitor with this index from
+                           --list-monitors, at startup and with F11
+  --list-monitors          Print the available monitors and exit
+  --verbose                Print adapter, device and surface details on startup
+  -h, --help               Print this help";
+
+// Settings for recording a clip. Time advances by exactly `1 / fps` per frame,
+// so the output doesn't depend on how fast frames render.
+pub struct Record {
+    pub path: PathBuf,
+    pub frames: u32,
+    pub fps: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// Settings for a benchmark run, which renders offscreen without presenting
+pub struct Bench {
+    pub frames: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// Settings for saving frames while running interactively
+pub struct FrameDump {
+    pub dir: PathBuf,
+    pub every: u32,
+}
+
+// Settings for a headless render, which skips the window and surface entirely
+pub struct Headless {
+    pub width: u32,
+    pub height: u32,
+    pub frame: u32,
+    // Overrides the frame's time, see --time
+    pub time: Option<f32>,
+    pub out: PathBuf,
+}
+
+// Which of wgpu's built-in limit sets to request the device with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LimitsPreset {
+    Default,
+    Downlevel,
+    Webgl2,
+}
+
+// How the colors a shader writes become display values, the same whatever the
+// surface format is
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Colorspace {
+    // Linear light, encoded to sRGB for display
+    Srgb,
+    // Already display values, shown unchanged
+    Linear,
+}
+
+// What each shader pass draws to cover the screen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FullscreenGeometry {
+    Triangle,
+    Quad,
+}
+
+// Which noise the built-in shader's grain comes from. The values are what the
+// `grain` uniform holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Grain {
+    Value = 0,
+    Blue = 1,
+}
+
+// A finishing effect of `--post`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PostEffect {
+    Vignette,
+    Scanlines,
+    ChromaticAberration,
+}
+
+// Tone mapping operator of the final pass. The values are what the blit
+// shader's `tonemap` uniform holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tonemap {
+    None = 0,
+    Reinhard = 1,
+    Aces = 2,
+    Filmic = 3,
+}
+
+pub const DEFAULT_CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.1,
+    g: 0.2,
+    b: 0.3,
+    a: 1.0,
+};
+
+pub struct Options {
+    pub shader_path: Option<PathBuf>,
+    pub fs_entry: Option<String>,
+    pub vs_entry: Option<String>,
+    pub dump_wgsl: Option<PathBuf>,
+    pub shadertoy: bool,
+    pub headless: Option<Headless>,
+    pub record: Option<Record>,
+    pub bench: Option<Bench>,
+    pub frame_dump: Option<FrameDump>,
+    pub backends: wgpu::Backends,
+    pub adapter: Option<usize>,
+    pub power_preference: wgpu::PowerPreference,
+    pub list_adapters: bool,
+    pub monitor: Option<usize>,
+    pub list_monitors: bool,
+    pub verbose: bool,
+    pub limits: Option<LimitsPreset>,
+    pub msaa: u32,
+    pub fixed_fps: Option<f32>,
+    pub speed: f32,
+    pub step: Option<f32>,
+    pub vsync: bool,
+    pub present_mode: Option<wgpu::PresentMode>,
+    pub fps_cap: Option<f32>,
+    pub channels: [Option<ChannelSpec>; MAX_CHANNELS],
+    pub mipmaps: bool,
+    pub scale: f32,
+    pub load_op: wgpu::LoadOp<wgpu::Color>,
+    pub colorspace: Colorspace,
+    // `None` leaves it to the surface, or sRGB RGBA8 offscreen
+    pub format: Option<wgpu::TextureFormat>,
+    pub fullscreen_geometry: FullscreenGeometry,
+    // `None` culls nothing
+    pub cull_mode: Option<wgpu::Face>,
+    pub front_face: wgpu::FrontFace,
+    pub depth: bool,
+    pub compute: bool,
+    pub seed: u32,
+    pub grain: Grain,
+    pub palette: Option<Palette>,
+    pub tonemap: Tonemap,
+    pub post: Vec<PostEffect>,
+    pub exposure: f32,
+    pub fullscreen: bool,
+    pub windowed: Option<(u32, u32)>,
+    pub title: Option<String>,
+    pub title_stats: bool,
+    pub exclusive: bool,
+    pub video_mode: Option<VideoModeRequest>,
+    pub list_video_modes: bool,
+    // `-h`/`--help`, which prints USAGE instead of running
+    pub help: bool,
+}
+
+// A command line `Options::parse` rejected, shown with USAGE after it
+#[derive(Debug)]
+pub struct UsageError(pub String);
+
+impl std::fmt::Display for UsageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}\n\n{}", self.0, USAGE)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+// The `--video-mode` to look for, with the refresh rate in hertz
+#[derive(Clone, Copy)]
+pub struct VideoModeRequest {
+    pub width: u32,
+    pub height: u32,
+    pub refresh_rate: Option<u32>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            shader_path: None,
+            fs_entry: None,
+            vs_entry: None,
+            dump_wgsl: None,
+            shadertoy: false,
+            headless: None,
+            record: None,
+            bench: None,
+            frame_dump: None,
+            backends: wgpu::Backends::all(),
+            adapter: None,
+            power_preference: wgpu::PowerPreference::LowPower,
+            list_adapters: false,
+            monitor: None,
+            list_monitors: false,
+            verbose: false,
+            limits: None,
+            msaa: 1,
+            fixed_fps: None,
+            speed: 1.0,
+            step: None,
+            vsync: true,
+            present_mode: None,
+            fps_cap: None,
+            channels: Default::default(),
+            mipmaps: false,
+            scale: 1.0,
+            load_op: wgpu::LoadOp::Clear(DEFAULT_CLEAR_COLOR),
+            colorspace: Colorspace::Srgb,
+            format: None,
+            fullscreen_geometry: FullscreenGeometry::Triangle,
+            cull_mode: Some(wgpu::Face::Back),
+            front_face: wgpu::FrontFace::Ccw,
+            depth: false,
+            compute: false,
+            seed: 0,
+            grain: Grain::Value,
+            palette: None,
+            tonemap: Tonemap::None,
+            post: Vec::new(),
+            exposure: 0.0,
+            fullscreen: false,
+            windowed: None,
+            title: None,
+            title_stats: false,
+            exclusive: false,
+            video_mode: None,
+            list_video_modes: false,
+            help: false,
+        }
+    }
+}
+
+impl Options {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut headless = false;
+        let mut headless_size = None;
+        let mut frame = None;
+        let mut time = None;
+        let mut out = None;
+        let mut record_path = None;
+        let mut duration = None;
+        let mut fps = None;
+        let mut size = None;
+        let mut bench = false;
+        let mut vsync = None;
+        let mut frames = None;
+        let mut frame_dump_dir = None;
+        let mut every = None;
+        let mut clear_color = None;
+        let mut no_clear = false;
+        let mut msaa = None;
+        let mut config_path = None;
+        let mut power = None;
+        let mut scale_given = false;
+        let mut ssaa = None;
+
+        let mut args = args.into_iter().peekable();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                // The size is optional, and can be given with --size instead
+                "--headless" => {
+                    headless = true;
+                    headless_size = args
+                        .next_if(|value| parse_size(value).is_ok())
+                        .and_then(|value| parse_size(&value).ok());
+                }
+                "--frame" => frame = Some(parse_number(&arg, &value(&arg, &mut args)?)?),
+                "--time" => {
+                    let seconds: f32 = parse_number(&arg, &value(&arg, &mut args)?)?;
+                    if seconds < 0.0 {
+                        return Err("--time can't be negative".to_string());
+                    }
+                    time = Some(seconds);
+                }
+                "--out" => out = Some(PathBuf::from(value(&arg, &mut args)?)),
+                "--record" => record_path = Some(PathBuf::from(value(&arg, &mut args)?)),
+                "--duration" => duration = Some(parse_number(&arg, &value(&arg, &mut args)?)?),
+                "--fps" => fps = Some(parse_number(&arg, &value(&arg, &mut args)?)?),
+                "--bench" => bench = true,
+                "--frames" => frames = Some(parse_number(&arg, &value(&arg, &mut args)?)?),
+                "--frame-dump-dir" => frame_dump_dir = Some(PathBuf::from(value(&arg, &mut args)?)),
+                "--every" => every = Some(parse_number(&arg, &value(&arg, &mut args)?)?),
+                "--size" => size = Some(parse_size(&value(&arg, &mut args)?)?),
+                "--backend" => options.backends = parse_backend(&value(&arg, &mut args)?)?,
+                "--adapter" => {
+                    options.adapter = Some(parse_number(&arg, &value(&arg, &mut args)?)?)
+                }
+                "--list-adapters" => options.list_adapters = true,
+                "--power" => power = Some(parse_power_preference(&value(&arg, &mut args)?)?),
+                "--monitor" => {
+                    options.monitor = Some(parse_number(&arg, &value(&arg, &mut args)?)?)
+                }
+                "--list-monitors" => options.list_monitors = true,
+                "--fullscreen" => options.fullscreen = true,
+                "--windowed" => options.windowed = Some(parse_size(&value(&arg, &mut args)?)?),
+                "--title" => options.title = Some(value(&arg, &mut args)?),
+                "--title-stats" => options.title_stats = true,
+                "--exclusive" => options.exclusive = true,
+                "--video-mode" => {
+                    options.video_mode = Some(parse_video_mode(&value(&arg, &mut args)?)?)
+                }
+                "--list-video-modes" => options.list_video_modes = true,
+                "--verbose" => options.verbose = true,
+                "--limits" => options.limits = Some(parse_limits(&value(&arg, &mut args)?)?),
+                "--fixed-fps" => {
+                    let fps: f32 = parse_number(&arg, &value(&arg, &mut args)?)?;
+                    if fps <= 0.0 {
+                        return Err("--fixed-fps must be positive".to_string());
+                    }
+                    options.fixed_fps = Some(fps);
+                }
+                "--speed" => options.speed = parse_number(&arg, &value(&arg, &mut args)?)?,
+                "--vsync" => {
+                    vsync = match value(&arg, &mut args)?.as_str() {
+                        "on" => Some(true),
+                        "off" => Some(false),
+                        other => {
+                            return Err(format!("unknown vsync '{}', expected on or off", other))
+                        }
+                    }
+                }
+                "--present-mode" => {
+                    options.present_mode = Some(parse_present_mode(&value(&arg, &mut args)?)?)
+                }
+                "--fps-cap" => {
+                    let fps: f32 = parse_number(&arg, &value(&arg, &mut args)?)?;
+                    if fps <= 0.0 {
+                        return Err("--fps-cap must be positive".to_string());
+                    }
+                    options.fps_cap = Some(fps);
+                }
+                "--fs-entry" => options.fs_entry = Some(value(&arg, &mut args)?),
+                "--vs-entry" => options.vs_entry = Some(value(&arg, &mut args)?),
+                "--dump-wgsl" => options.dump_wgsl = Some(value(&arg, &mut args)?.into()),
+                "--shadertoy" => options.shadertoy = true,
+                "--step" => {
+                    let step: f32 = parse_number(&arg, &value(&arg, &mut args)?)?;
+                    if step <= 0.0 {
+                        return Err("--step must be positive".to_string());
+                    }
+                    options.step = Some(step);
+                }
+                "--texture" => {
+                    options.channels[0] = Some(ChannelSpec::Image(value(&arg, &mut args)?.into()))
+                }
+                "--channel0" | "--channel1" | "--channel2" | "--channel3" => {
+                    // The flag ends in the channel's digit
+                    let index = usize::from(arg.as_bytes()[arg.len() - 1] - b'0');
+                    options.channels[index] = Some(ChannelSpec::parse(&value(&arg, &mut args)?));
+                }
+                "--mipmaps" => options.mipmaps = true,
+                "--compute" => options.compute = true,
+                "--depth" => options.depth = true,
+                "--seed" => options.seed = parse_number(&arg, &value(&arg, &mut args)?)?,
+                "--colorspace" => options.colorspace = parse_colorspace(&value(&arg, &mut args)?)?,
+                "--format" => options.format = Some(parse_format(&value(&arg, &mut args)?)?),
+                "--fullscreen-geometry" => {
+                    options.fullscreen_geometry =
+                        parse_fullscreen_geometry(&value(&arg, &mut args)?)?
+                }
+                "--cull" => options.cull_mode = parse_cull_mode(&value(&arg, &mut args)?)?,
+                "--front-face" => options.front_face = parse_front_face(&value(&arg, &mut args)?)?,
+                "--grain" => options.grain = parse_grain(&value(&arg, &mut args)?)?,
+                "--palette" => {
+                    options.palette = Some(
+                        Palette::from_arg(&value(&arg, &mut args)?)
+                            .map_err(|err| format!("invalid palette for '{}': {}", arg, err))?,
+                    )
+                }
+                "--post" => {
+                    options.post = value(&arg, &mut args)?
+                        .split(',')
+                        .map(parse_post_effect)
+                        .collect::<Result<_, _>>()?
+                }
+                "--tonemap" => options.tonemap = parse_tonemap(&value(&arg, &mut args)?)?,
+                "--exposure" => options.exposure = parse_number(&arg, &value(&arg, &mut args)?)?,
+                "--scale" => {
+                    options.scale = parse_number(&arg, &value(&arg, &mut args)?)?;
+                    if options.scale <= 0.0 {
+                        return Err("--scale must be positive".to_string());
+                    }
+                    scale_given = true;
+                }
+                "--ssaa" => {
+                    let factor: u32 = parse_number(&arg, &value(&arg, &mut args)?)?;
+                    if factor != 2 && factor != 4 {
+                        return Err(format!("invalid --ssaa factor {}, expected 2 or 4", factor));
+                    }
+                    ssaa = Some(factor);
+                }
+                "--clear-color" => clear_color = Some(parse_color(&value(&arg, &mut args)?)?),
+                "--no-clear" => no_clear = true,
+                "--msaa" => msaa = Some(parse_number(&arg, &value(&arg, &mut args)?)?),
+                "--config" => config_path = Some(PathBuf::from(value(&arg, &mut args)?)),
+                // Nothing else matters once help is asked for
+                "-h" | "--help" => {
+                    options.help = true;
+                    return Ok(options);
+                }
+                _ if arg.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+                _ if options.shader_path.is_none() => options.shader_path = Some(arg.into()),
+                _ => return Err(format!("unexpected argument '{}'", arg)),
+            }
+        }
+
+        if let Some(factor) = ssaa {
+            if scale_given {
+                return Err("--ssaa and --scale can't be combined".to_string());
+            }
+            options.scale = factor as f32;
+        }
+
+        // The config fills in for whatever the flags leave unset
+        if let Some(path) = &config_path {
+            let config = Config::load(path)?;
+            let invalid = |err: String| format!("invalid config '{}': {}", path.display(), err);
+            if options.shader_path.is_none() {
+                options.shader_path = config.shader_path.clone();
+            }
+            if !options.fullscreen && !options.exclusive && options.windowed.is_none() {
+                if config.fullscreen && config.windowed().is_some() {
+                    return Err(invalid(
+                        "fullscreen can't be combined with width and height".to_string(),
+                    ));
+                }
+                options.fullscreen = config.fullscreen;
+                options.windowed = config.windowed();
+            }
+            if vsync.is_none() && options.present_mode.is_none() {
+                options.present_mode = config
+                    .present_mode
+                    .as_deref()
+                    .map(parse_present_mode)
+                    .transpose()
+                    .map_err(invalid)?;
+            }
+            msaa = msaa.or(config.msaa);
+        }
+
+        if let Some(msaa) = msaa {
+            if ![1, 2, 4, 8].contains(&msaa) {
+                return Err(format!(
+                    "invalid sample count {}, expected 1, 2, 4 or 8",
+                    msaa
+                ));
+            }
+            options.msaa = msaa;
+        }
+
+        // An explicit adapter leaves nothing for the preference to pick
+        if let Some(power) = power {
+            if options.adapter.is_some() {
+                return Err("--power and --adapter can't be combined".to_string());
+            }
+            options.power_preference = power;
+        }
+
+        match (vsync, options.present_mode) {
+            (Some(_), Some(_)) => {
+                return Err("--vsync and --present-mode can't be combined".to_string())
+            }
+            (Some(vsync), None) => options.vsync = vsync,
+            (None, Some(mode)) => options.vsync = mode == wgpu::PresentMode::Fifo,
+            (None, None) => {}
+        }
+
+        // Compute shaders write every pixel themselves, without a render pass
+        if options.compute {
+            if options.msaa != 1 || clear_color.is_some() || no_clear || options.depth {
+                return Err(
+                    "--msaa, --clear-color, --no-clear and --depth don't apply to --compute"
+                        .to_string(),
+                );
+            }
+            if options.limits == Some(LimitsPreset::Webgl2) {
+                return Err(
+                    "--compute needs compute shaders, which the webgl2 limits don't allow"
+                        .to_string(),
+                );
+            }
+        }
+
+        match (clear_color, no_clear) {
+            (Some(_), true) => {
+                return Err("--clear-color and --no-clear can't be combined".to_string())
+            }
+            (Some(color), false) => options.load_op = wgpu::LoadOp::Clear(color),
+            (None, true) => options.load_op = wgpu::LoadOp::Load,
+            (None, false) => {}
+        }
+
+        if (options.fullscreen || options.exclusive) && options.windowed.is_some() {
+            return Err(
+                "--fullscreen and --exclusive can't be combined with --windowed".to_string(),
+            );
+        }
+        if options.video_mode.is_some() && !options.exclusive {
+            return Err("--video-mode requires --exclusive".to_string());
+        }
+
+        let has_image = options
+            .channels
+            .iter()
+            .any(|spec| matches!(spec, Some(ChannelSpec::Image(_))));
+        if options.mipmaps && !has_image {
+            return Err("--mipmaps requires an image channel".to_string());
+        }
+
+        match headless {
+            true if frame.is_some() && time.is_some() => {
+                return Err("--frame and --time can't be combined".to_string());
+            }
+            true if headless_size.is_some() && size.is_some() => {
+                return Err("--headless WIDTHxHEIGHT and --size can't be combined".to_string());
+            }
+            true => {
+                let (width, height) = headless_size.or(size.take()).unwrap_or((1280, 720));
+                options.headless = Some(Headless {
+                    width,
+                    height,
+                    frame: frame.unwrap_or(0),
+                    time,
+                    out: out.unwrap_or_else(|| PathBuf::from("frame.png")),
+                });
+            }
+            false if frame.is_some() || time.is_some() || out.is_some() => {
+                return Err("--frame, --time and --out require --headless".to_string());
+            }
+            false => {}
+        }
+
+        let duration_given = duration.is_some();
+        match record_path {
+            Some(path) => {
+                let (width, height) = size.unwrap_or((1280, 720));
+                let fps = fps.unwrap_or(60);
+                let duration: f32 = duration.unwrap_or(10.0);
+                if duration <= 0.0 || fps == 0 {
+                    return Err("--duration and --fps must be positive".to_string());
+                }
+                let frames = match frames.take() {
+                    Some(_) if duration_given => {
+                        return Err("--duration and --frames can't be combined".to_string());
+                    }
+                    Some(0) => return Err("--frames must be positive".to_string()),
+                    Some(frames) => frames,
+                    None => (duration * fps as f32).round() as u32,
+                };
+                options.record = Some(Record {
+                    path,
+                    frames,
+                    fps,
+                    width,
+                    height,
+                });
+            }
+            None if bench => {
+                let (width, height) = size.unwrap_or((1280, 720));
+                let bench = Bench {
+                    frames: frames.unwrap_or(1000),
+                    width,
+                    height,
+                };
+                if bench.frames == 0 {
+                    return Err("--frames must be positive".to_string());
+                }
+                options.bench = Some(bench);
+            }
+            None if duration.is_some() || fps.is_some() => {
+                return Err("--duration and --fps require --record".to_string());
+            }
+            None if size.is_some() => {
+                return Err("--size requires --headless, --record or --bench".to_string());
+            }
+            None => {}
+        }
+        if options.bench.is_none() && frames.is_some() {
+            return Err("--frames requires --bench or --record".to_string());
+        }
+
+        match frame_dump_dir {
+            Some(dir) => {
+                if options.headless.is_some() || options.record.is_some() || options.bench.is_some()
+                {
+                    return Err(
+                        "--frame-dump-dir can't be combined with --headless, --record or --bench"
+                            .to_string(),
+                    );
+                }
+                let every = every.unwrap_or(1);
+                if every == 0 {
+                    return Err("--every must be positive".to_string());
+                }
+                options.frame_dump = Some(FrameDump { dir, every });
+            }
+            None if every.is_some() => {
+                return Err("--every requires --frame-dump-dir".to_string());
+            }
+            None => {}
+        }
+
+        if options.fs_entry.is_some() || options.vs_entry.is_some() {
+            if options.shader_path.is_none() {
+                return Err("--fs-entry and --vs-entry require a SHADER".to_string());
+            }
+            if options.compute {
+                return Err(
+                    "--fs-entry and --vs-entry can't be combined with --compute".to_string()
+                );
+            }
+        }
+
+        if options.shadertoy {
+            if options.shader_path.is_none() {
+                return Err("--shadertoy requires a SHADER".to_string());
+            }
+            if options.compute || options.fs_entry.is_some() || options.vs_entry.is_some() {
+                return Err(
+                    "--shadertoy can't be combined with --compute, --fs-entry or --vs-entry"
+                        .to_string(),
+                );
+            }
+        }
+
+        // Video frames and dumped frames are 8 bits per channel
+        if options.format == Some(wgpu::TextureFormat::Rgba16Float)
+            && (options.record.is_some() || options.frame_dump.is_some())
+        {
+            return Err(
+                "--format rgba16float can't be combined with --record or --frame-dump-dir"
+                    .to_string(),
+            );
+        }
+
+        Ok(options)
+    }
+}
+
+fn value(flag: &str, args: &mut impl Iterator<Item = String>) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("missing value for '{}'", flag))
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for '{}'", value, flag))
+}
+
+fn parse_backend(value: &str) -> Result<wgpu::Backends, String> {
+    match value {
+        "vulkan" => Ok(wgpu::Backends::VULKAN),
+        "dx12" => Ok(wgpu::Backends::DX12),
+        "metal" => Ok(wgpu::Backends::METAL),
+        "gl" => Ok(wgpu::Backends::GL),
+        _ => Err(format!(
+            "unknown backend '{}', expected vulkan, dx12, metal or gl",
+            value
+        )),
+    }
+}
+
+fn parse_colorspace(value: &str) -> Result<Colorspace, String> {
+    match value {
+        "srgb" => Ok(Colorspace::Srgb),
+        "linear" => Ok(Colorspace::Linear),
+        _ => Err(format!(
+            "unknown colorspace '{}', expected srgb or linear",
+            value
+        )),
+    }
+}
+
+fn parse_format(value: &str) -> Result<wgpu::TextureFormat, String> {
+    match value {
+        "rgba8unorm" => Ok(wgpu::TextureFormat::Rgba8Unorm),
+        "rgba8unorm-srgb" => Ok(wgpu::TextureFormat::Rgba8UnormSrgb),
+        "bgra8unorm" => Ok(wgpu::TextureFormat::Bgra8Unorm),
+        "bgra8unorm-srgb" => Ok(wgpu::TextureFormat::Bgra8UnormSrgb),
+        "rgba16float" => Ok(wgpu::TextureFormat::Rgba16Float),
+        _ => Err(format!(
+            "unknown format '{}', expected rgba8unorm, rgba8unorm-srgb, bgra8unorm, \
+             bgra8unorm-srgb or rgba16float",
+            value
+        )),
+    }
+}
+
+fn parse_fullscreen_geometry(value: &str) -> Result<FullscreenGeometry, String> {
+    match value {
+        "triangle" => Ok(FullscreenGeometry::Triangle),
+        "quad" => Ok(FullscreenGeometry::Quad),
+        _ => Err(format!(
+            "unknown fullscreen geometry '{}', expected triangle or quad",
+            value
+        )),
+    }
+}
+
+fn parse_power_preference(value: &str) -> Result<wgpu::PowerPreference, String> {
+    match value {
+        "low" => Ok(wgpu::PowerPreference::LowPower),
+        "high" => Ok(wgpu::PowerPreference::HighPerformance),
+        _ => Err(format!(
+            "unknown power preference '{}', expected low or high",
+            value
+        )),
+    }
+}
+
+fn parse_present_mode(value: &str) -> Result<wgpu::PresentMode, String> {
+    match value {
+        "fifo" => Ok(wgpu::PresentMode::Fifo),
+        "mailbox" => Ok(wgpu::PresentMode::Mailbox),
+        "immediate" => Ok(wgpu::PresentMode::Immediate),
+        _ => Err(format!(
+            "unknown present mode '{}', expected fifo, mailbox or immediate",
+            value
+        )),
+    }
+}
+
+fn parse_cull_mode(value: &str) -> Result<Option<wgpu::Face>, String> {
+    match value {
+        "none" => Ok(None),
+        "back" => Ok(Some(wgpu::Face::Back)),
+        "front" => Ok(Some(wgpu::Face::Front)),
+        _ => Err(format!(
+            "unknown cull mode '{}', expected none, back or front",
+            value
+        )),
+    }
+}
+
+fn parse_front_face(value: &str) -> Result<wgpu::FrontFace, String> {
+    match value {
+        "ccw" => Ok(wgpu::FrontFace::Ccw),
+        "cw" => Ok(wgpu::FrontFace::Cw),
+        _ => Err(format!(
+            "unknown front face '{}', expected ccw or cw",
+            value
+        )),
+    }
+}
+
+fn parse_grain(value: &str) -> Result<Grain, String> {
+    match value {
+        "value" => Ok(Grain::Value),
+        "blue" => Ok(Grain::Blue),
+        _ => Err(format!("unknown grain '{}', expected value or blue", value)),
+    }
+}
+
+fn parse_post_effect(value: &str) -> Result<PostEffect, String> {
+    match value {
+        "vignette" => Ok(PostEffect::Vignette),
+        "scanlines" => Ok(PostEffect::Scanlines),
+        "chromatic-aberration" => Ok(PostEffect::ChromaticAberration),
+        _ => Err(format!(
+            "unknown post effect '{}', expected vignette, scanlines or chromatic-aberration",
+            value
+        )),
+    }
+}
+
+fn parse_tonemap(value: &str) -> Result<Tonemap, String> {
+    match value {
+        "none" => Ok(Tonemap::None),
+        "reinhard" => Ok(Tonemap::Reinhard),
+        "aces" => Ok(Tonemap::Aces),
+        "filmic" => Ok(Tonemap::Filmic),
+        _ => Err(format!(
+            "unknown tonemap '{}', expected none, reinhard, aces or filmic",
+            value
+        )),
+    }
+}
+
+fn parse_limits(value: &str) -> Result<LimitsPreset, String> {
+    match value {
+        "default" => Ok(LimitsPreset::Default),
+        "downlevel" => Ok(LimitsPreset::Downlevel),
+        "webgl2" => Ok(LimitsPreset::Webgl2),
+        _ => Err(format!(
+            "unknown limits '{}', expected default, downlevel or webgl2",
+            value
+        )),
+    }
+}
+
+// Parse an opaque `R,G,B` color such as `0.1,0.2,0.3`
+fn parse_color(value: &str) -> Result<wgpu::Color, String> {
+    let invalid = || {
+        format!(
+            "invalid color '{}', expected R,G,B with components between 0 and 1",
+            value
+        )
+    };
+    let components = value
+        .split(',')
+        .map(|c| c.trim().parse::<f64>().map_err(|_| invalid()))
+        .collect::<Result<Vec<_>, _>>()?;
+    match components[..] {
+        [r, g, b] if components.iter().all(|c| (0.0..=1.0).contains(c)) => {
+            Ok(wgpu::Color { r, g, b, a: 1.0 })
+        }
+        _ => Err(invalid()),
+    }
+}
+
+// Parse `--video-mode`: a `WIDTHxHEIGHT` size with an optional `@HZ` refresh
+// rate, such as `1920x1080@144`
+fn parse_video_mode(value: &str) -> Result<VideoModeRequest, String> {
+    let (size, refresh_rate) = match value.split_once('@') {
+        Some((size, rate)) => {
+            let rate = rate
+                .parse()
+                .ok()
+                .filter(|&rate| rate > 0)
+                .ok_or_else(|| format!("invalid refresh rate '{}' in --video-mode", rate))?;
+            (size, Some(rate))
+        }
+        None => (value, None),
+    };
+    let (width, height) = parse_size(size)?;
+    Ok(VideoModeRequest {
+        width,
+        height,
+        refresh_rate,
+    })
+}
+
+// Parse a `WIDTHxHEIGHT` pair such as `1280x720`
+fn parse_size(value: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("invalid size '{}', expected WIDTHxHEIGHT", value);
+    let (width, height) = value.split_once('x').ok_or_else(invalid)?;
+    let width: u32 = width.parse().map_err(|_| invalid())?;
+    let height: u32 = height.parse().map_err(|_| invalid())?;
+    if width == 0 || height == 0 {
+        return Err(invalid());
+    }
+    Ok((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn headless_size(args: &[&str]) -> (u32, u32) {
+        let headless = parse(args).unwrap().headless.unwrap();
+        (headless.width, headless.height)
+    }
+
+    #[test]
+    fn headless_size_from_either_flag() {
+        assert_eq!(headless_size(&["--headless", "320x180"]), (320, 180));
+        assert_eq!(
+            headless_size(&["--headless", "--size", "640x360", "--time", "1.5"]),
+            (640, 360)
+        );
+        assert_eq!(
+            headless_size(&["--size", "640x360", "--headless"]),
+            (640, 360)
+        );
+        assert_eq!(headless_size(&["--headless"]), (1280, 720));
+
+        // Without a size, what follows is left to be parsed as usual
+        let options = parse(&["--headless", "shader.wgsl"]).unwrap();
+        assert_eq!(options.shader_path, Some(PathBuf::from("shader.wgsl")));
+        assert!(parse(&["--headless", "320x180", "--size", "640x360"]).is_err());
+    }
+
+    #[test]
+    fn ssaa_sets_scale() {
+        assert_eq!(parse(&["--ssaa", "2"]).unwrap().scale, 2.0);
+        assert_eq!(parse(&["--ssaa", "4"]).unwrap().scale, 4.0);
+        assert!(parse(&["--ssaa", "3"]).is_err());
+        assert!(parse(&["--ssaa", "2", "--scale", "2"]).is_err());
+    }
+
+    #[test]
+    fn size_needs_an_offscreen_mode() {
+        assert!(parse(&["--size", "640x360"]).is_err());
+        assert!(parse(&["--bench", "--size", "640x360"]).is_ok());
+    }
+}
@@ -0,0 +1,248 @@
+use wgpu::util::DeviceExt;
+
+// Maximum number of characters the overlay can show at once
+const MAX_GLYPHS: usize = 32;
+
+// Size of one font pixel on screen, in physical pixels
+const PIXEL_SCALE: f32 = 3.0;
+
+// Draws a box in the top-left corner holding a short line of text. Each glyph
+// is a 3x5 bitmap packed into the low 15 bits of a u32, top row first, so the
+// shader can look pixels up without a font texture.
+const OVERLAY_SHADER: &str = r#"
+struct Overlay {
+    glyphs: array<vec4<u32>, 8>,
+    resolution: vec2<f32>,
+    len: u32,
+    scale: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> overlay: Overlay;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    // Position inside the box, in font pixels
+    @location(0) local: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 1.0)
+    );
+    // One font pixel of padding around the text, and 4 columns per glyph
+    // including the gap between them
+    let size = vec2<f32>(f32(overlay.len * 4u + 1u), 7.0);
+    let local = corners[vertex_index] * size;
+    let pixel = (local + vec2<f32>(1.0, 1.0)) * overlay.scale;
+    let clip = vec2<f32>(
+        pixel.x / overlay.resolution.x * 2.0 - 1.0,
+        1.0 - pixel.y / overlay.resolution.y * 2.0
+    );
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(clip, 0.0, 1.0);
+    out.local = local;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let background = vec4<f32>(0.0, 0.0, 0.0, 0.6);
+    let p = vec2<i32>(floor(in.local)) - vec2<i32>(1, 1);
+    if p.x < 0 || p.y < 0 || p.y >= 5 {
+        return background;
+    }
+
+    let cell = u32(p.x) / 4u;
+    let column = u32(p.x) % 4u;
+    if cell >= overlay.len || column == 3u {
+        return background;
+    }
+
+    let glyph = overlay.glyphs[cell / 4u][cell % 4u];
+    let bit = 14u - (u32(p.y) * 3u + column);
+    if ((glyph >> bit) & 1u) == 0u {
+        return background;
+    }
+    return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+}
+"#;
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct OverlayUniforms {
+    glyphs: [[u32; 4]; MAX_GLYPHS / 4],
+    resolution: [f32; 2],
+    len: u32,
+    scale: f32,
+}
+
+// Bitmap for `c` in the 3x5 font; characters without a glyph render as blanks
+fn glyph(c: char) -> u32 {
+    match c.to_ascii_uppercase() {
+        '0' => 0b111_101_101_101_111,
+        '1' => 0b010_110_010_010_111,
+        '2' => 0b111_001_111_100_111,
+        '3' => 0b111_001_111_001_111,
+        '4' => 0b101_101_111_001_001,
+        '5' => 0b111_100_111_001_111,
+        '6' => 0b111_100_111_101_111,
+        '7' => 0b111_001_001_001_001,
+        '8' => 0b111_101_111_101_111,
+        '9' => 0b111_101_111_001_111,
+        '.' => 0b000_000_000_000_010,
+        'F' => 0b111_100_110_100_100,
+        'G' => 0b111_100_101_101_111,
+        'M' => 0b101_111_111_101_101,
+        'P' => 0b110_101_110_100_100,
+        'S' => 0b011_100_010_001_110,
+        'U' => 0b101_101_101_101_111,
+        _ => 0,
+    }
+}
+
+// A single line of text drawn over the shader output
+pub struct Overlay {
+    uniforms: OverlayUniforms,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Overlay {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let uniforms = OverlayUniforms {
+            glyphs: [[0; 4]; MAX_GLYPHS / 4],
+            resolution: [1.0, 1.0],
+            len: 0,
+            scale: PIXEL_SCALE,
+        };
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Overlay Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[uniforms]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<OverlayUniforms>() as u64
+                    ),
+                },
+                count: None,
+            }],
+            label: Some("overlay_bind_group_layout"),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+            label: Some("overlay_bind_group"),
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Overlay Shader"),
+            source: wgpu::ShaderSource::Wgsl(OVERLAY_SHADER.into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Overlay Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Overlay Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            uniforms,
+            uniform_buffer,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    // Replace the displayed text, truncating anything past `MAX_GLYPHS`
+    pub fn set_text(&mut self, text: &str) {
+        self.uniforms.glyphs = [[0; 4]; MAX_GLYPHS / 4];
+        let mut len = 0;
+        for (i, c) in text.chars().take(MAX_GLYPHS).enumerate() {
+            self.uniforms.glyphs[i / 4][i % 4] = glyph(c);
+            len = i + 1;
+        }
+        self.uniforms.len = len as u32;
+    }
+
+    // Record the overlay on top of whatever is already in `view`
+    pub fn draw(
+        &mut self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        width: u32,
+        height: u32,
+    ) {
+        if self.uniforms.len == 0 {
+            return;
+        }
+
+        self.uniforms.resolution = [width as f32, height as f32];
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[self.uniforms]),
+        );
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Overlay Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..6, 0..1);
+    }
+}
@@ -0,0 +1,92 @@
+use std::fs;
+use std::path::Path;
+
+// Most colors a palette can hold, the length of the `palette` uniform array
+pub const MAX_COLORS: usize = 8;
+
+// Colors the built-in shader can recolor its pattern with, blending smoothly
+// from each to the next and from the last back to the first
+#[derive(Clone, Debug)]
+pub struct Palette {
+    pub name: String,
+    // Linear RGBA, decoded from the sRGB hex values they were written as
+    colors: Vec<[f32; 4]>,
+}
+
+impl Palette {
+    // What `--palette` takes: a file of hex colors, one per line or separated
+    // by commas, or a comma-separated list of them
+    pub fn from_arg(value: &str) -> Result<Self, String> {
+        let path = Path::new(value);
+        if path.is_file() {
+            let text = fs::read_to_string(path)
+                .map_err(|err| format!("failed to read '{}': {}", path.display(), err))?;
+            return Self::parse(&path.display().to_string(), &text)
+                .map_err(|err| format!("'{}': {}", path.display(), err));
+        }
+        Self::parse(value, value)
+    }
+
+    // Colors are `RRGGBB` or `RRGGBBAA`, with or without a leading `#`
+    fn parse(name: &str, text: &str) -> Result<Self, String> {
+        let colors = text
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|color| !color.is_empty())
+            .map(|color| parse_hex(color).ok_or_else(|| format!("invalid color '{}'", color)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if !(2..=MAX_COLORS).contains(&colors.len()) {
+            return Err(format!(
+                "a palette needs 2 to {} colors, got {}",
+                MAX_COLORS,
+                colors.len()
+            ));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            colors,
+        })
+    }
+
+    // The palettes C cycles through after the one from `--palette`
+    pub fn builtin() -> Vec<Self> {
+        [
+            ("Sunset", "1a0533,5c1a5e,b3305a,f0643c,ffb347,ffe08a"),
+            ("Ocean", "021024,052659,1b5e8c,2f9fbd,7dd3e0,e0f7fa"),
+            ("Fire", "000000,5a0000,c21f00,ff7a00,ffd200,ffffff"),
+            ("Neon", "ff00ff,00ffff,39ff14,ffff00"),
+        ]
+        .into_iter()
+        .map(|(name, colors)| Self::parse(name, colors).expect("built-in palettes are valid"))
+        .collect()
+    }
+
+    // The color count and array the `palette_size` and `palette` uniforms hold,
+    // with the unused entries zeroed
+    pub fn entries(&self) -> (u32, [[f32; 4]; MAX_COLORS]) {
+        let mut entries = [[0.0; 4]; MAX_COLORS];
+        entries[..self.colors.len()].copy_from_slice(&self.colors);
+        (self.colors.len() as u32, entries)
+    }
+}
+
+fn parse_hex(color: &str) -> Option<[f32; 4]> {
+    let hex = color.strip_prefix('#').unwrap_or(color);
+    if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+        return None;
+    }
+    let mut channels = [0.0, 0.0, 0.0, 1.0];
+    for (i, channel) in channels.iter_mut().enumerate().take(hex.len() / 2) {
+        let value = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()? as f32 / 255.0;
+        // Alpha isn't gamma encoded
+        *channel = if i == 3 { value } else { srgb_decode(value) };
+    }
+    Some(channels)
+}
+
+fn srgb_decode(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
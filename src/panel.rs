@@ -0,0 +1,107 @@
+use crate::Uniforms;
+use winit::event::WindowEvent;
+use winit::event_loop::EventLoopWindowTarget;
+use winit::window::Window;
+
+// Sliders for the shader's tweakable uniforms, drawn with egui over the frame
+pub struct Panel {
+    context: egui::Context,
+    state: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+    pub visible: bool,
+}
+
+impl Panel {
+    pub fn new(
+        event_loop: &EventLoopWindowTarget<()>,
+        window: &Window,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let mut state = egui_winit::State::new(event_loop);
+        state.set_pixels_per_point(window.scale_factor() as f32);
+        Self {
+            context: egui::Context::default(),
+            state,
+            renderer: egui_wgpu::Renderer::new(device, format, None, 1),
+            visible: false,
+        }
+    }
+
+    // Feed a window event to egui. Returns true when the panel is open and
+    // egui wants the event to itself, e.g. while dragging a slider.
+    pub fn on_event(&mut self, event: &WindowEvent) -> bool {
+        let response = self.state.on_event(&self.context, event);
+        self.visible && response.consumed
+    }
+
+    // Lay out the panel, apply slider changes to `uniforms` and record the
+    // result on top of whatever is already in `view`
+    pub fn draw(
+        &mut self,
+        window: &Window,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        uniforms: &mut Uniforms,
+    ) {
+        let input = self.state.take_egui_input(window);
+        let output = self.context.run(input, |context| {
+            egui::Window::new("Parameters")
+                .resizable(false)
+                .show(context, |ui| parameters(ui, uniforms));
+        });
+        self.state
+            .handle_platform_output(window, &self.context, output.platform_output);
+
+        let size = window.inner_size();
+        let screen = egui_wgpu::renderer::ScreenDescriptor {
+            size_in_pixels: [size.width, size.height],
+            pixels_per_point: window.scale_factor() as f32,
+        };
+        let primitives = self.context.tessellate(output.shapes);
+        for (id, delta) in &output.textures_delta.set {
+            self.renderer.update_texture(device, queue, *id, delta);
+        }
+        // Any extra command buffers come from paint callbacks, which the panel
+        // doesn't use
+        self.renderer
+            .update_buffers(device, queue, encoder, &primitives, &screen);
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Panel Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            self.renderer.render(&mut render_pass, &primitives, &screen);
+        }
+
+        for id in &output.textures_delta.free {
+            self.renderer.free_texture(id);
+        }
+    }
+}
+
+fn parameters(ui: &mut egui::Ui, uniforms: &mut Uniforms) {
+    let center = &mut uniforms.center;
+    ui.add(egui::Slider::new(&mut center[0], 0.0..=1.0).text("Center X"));
+    ui.add(egui::Slider::new(&mut center[1], 0.0..=1.0).text("Center Y"));
+
+    let frequencies = &mut uniforms.frequencies;
+    ui.add(egui::Slider::new(&mut frequencies[0], 0.0..=50.0).text("Horizontal waves"));
+    ui.add(egui::Slider::new(&mut frequencies[1], 0.0..=50.0).text("Vertical waves"));
+    ui.add(egui::Slider::new(&mut frequencies[2], 0.0..=50.0).text("Rings"));
+    ui.add(egui::Slider::new(&mut frequencies[3], 0.0..=50.0).text("Spiral"));
+
+    ui.add(egui::Slider::new(&mut uniforms.grain_intensity, 0.0..=1.0).text("Grain"));
+    ui.add(egui::Slider::new(&mut uniforms.pulse_speed, 0.0..=5.0).text("Pulse speed"));
+}
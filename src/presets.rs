@@ -0,0 +1,193 @@
+use crate::FRAGMENT_SHADER;
+
+// Classic plasma built from overlapping sine fields
+const PLASMA_SHADER: &str = r#"
+struct Uniforms {
+    time: f32,
+    delta: f32,
+    resolution: vec2<f32>,
+    mouse: vec4<f32>,
+    frame: u32,
+    grain_intensity: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let time = uniforms.time;
+    let p = pos.xy / uniforms.resolution * 8.0;
+
+    var v = sin(p.x + time);
+    v += sin((p.y + time) * 0.5);
+    v += sin((p.x + p.y + time) * 0.5);
+    let c = p + vec2<f32>(sin(time * 0.3), cos(time * 0.5)) * 4.0;
+    v += sin(sqrt(c.x * c.x + c.y * c.y + 1.0) + time);
+    v *= 0.5;
+
+    let color = vec3<f32>(sin(v * 3.14159), sin(v * 3.14159 + 2.094), sin(v * 3.14159 + 4.188));
+    return vec4<f32>(color * 0.5 + 0.5, 1.0);
+}
+"#;
+
+// Shows the normalized coordinates as red/green with a grid every tenth, which
+// makes orientation and resolution problems easy to spot
+const UV_DEBUG_SHADER: &str = r#"
+struct Uniforms {
+    time: f32,
+    delta: f32,
+    resolution: vec2<f32>,
+    mouse: vec4<f32>,
+    frame: u32,
+    grain_intensity: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = pos.xy / uniforms.resolution;
+    let cell = fract(uv * 10.0);
+    let line_width = 10.0 / uniforms.resolution;
+    let on_grid = any(cell < line_width) || any(cell > vec2<f32>(1.0) - line_width);
+
+    var color = vec3<f32>(uv, 0.0);
+    if on_grid {
+        color = vec3<f32>(1.0);
+    }
+    return vec4<f32>(color, 1.0);
+}
+"#;
+
+// Demonstrates the feedback buffer: a dot orbits the mouse and leaves a trail
+// because each frame keeps a faded, slightly zoomed copy of the previous one
+const FEEDBACK_SHADER: &str = r#"
+struct Uniforms {
+    time: f32,
+    delta: f32,
+    resolution: vec2<f32>,
+    mouse: vec4<f32>,
+    frame: u32,
+    grain_intensity: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+@group(0) @binding(1)
+var prev_frame: texture_2d<f32>;
+
+@group(0) @binding(2)
+var prev_sampler: sampler;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let time = uniforms.time;
+    let uv = pos.xy / uniforms.resolution;
+    let center = uniforms.mouse.xy / uniforms.resolution;
+
+    let zoomed = (uv - center) * 0.99 + center;
+    let trail = textureSample(prev_frame, prev_sampler, zoomed).rgb * 0.96;
+
+    let aspect = vec2<f32>(uniforms.resolution.x / uniforms.resolution.y, 1.0);
+    let dot_pos = center + vec2<f32>(cos(time * 2.0), sin(time * 3.0)) * 0.25 / aspect;
+    let d = length((uv - dot_pos) * aspect);
+    let hue = vec3<f32>(sin(time), sin(time + 2.094), sin(time + 4.188)) * 0.5 + 0.5;
+    let dot_color = hue * smoothstep(0.03, 0.02, d);
+
+    return vec4<f32>(max(trail, dot_color), 1.0);
+}
+"#;
+
+// Raymarches a bouncing sphere over a checkered floor and writes how far away
+// each pixel is as its depth, which --depth gives somewhere to go
+const RAYMARCH_SHADER: &str = r#"
+struct Uniforms {
+    time: f32,
+    delta: f32,
+    resolution: vec2<f32>,
+    mouse: vec4<f32>,
+    frame: u32,
+    grain_intensity: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+struct Output {
+    @location(0) color: vec4<f32>,
+    @builtin(frag_depth) depth: f32,
+};
+
+// Distance at which rays give up, mapped to depth 1
+const FAR: f32 = 20.0;
+
+fn scene(p: vec3<f32>) -> f32 {
+    let center = vec3<f32>(0.0, 1.0 + abs(sin(uniforms.time * 2.0)), 0.0);
+    return min(length(p - center) - 1.0, p.y);
+}
+
+fn normal(p: vec3<f32>) -> vec3<f32> {
+    let e = vec2<f32>(0.001, 0.0);
+    return normalize(vec3<f32>(
+        scene(p + e.xyy) - scene(p - e.xyy),
+        scene(p + e.yxy) - scene(p - e.yxy),
+        scene(p + e.yyx) - scene(p - e.yyx)
+    ));
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> Output {
+    let uv = (pos.xy * 2.0 - uniforms.resolution) / uniforms.resolution.y * vec2<f32>(1.0, -1.0);
+
+    // Orbit the sphere, looking at its base
+    let angle = uniforms.time * 0.3;
+    let eye = vec3<f32>(sin(angle) * 6.0, 2.5, cos(angle) * 6.0);
+    let forward = normalize(vec3<f32>(0.0, 1.0, 0.0) - eye);
+    let right = normalize(cross(forward, vec3<f32>(0.0, 1.0, 0.0)));
+    let up = cross(right, forward);
+    let dir = normalize(forward * 1.5 + right * uv.x + up * uv.y);
+
+    var t = 0.0;
+    for (var i = 0; i < 128; i++) {
+        let d = scene(eye + dir * t);
+        if d < 0.001 * t || t > FAR {
+            break;
+        }
+        t += d;
+    }
+
+    let sky = vec3<f32>(0.5, 0.7, 1.0) - dir.y * 0.3;
+    var out: Output;
+    if t > FAR {
+        out.color = vec4<f32>(sky, 1.0);
+        out.depth = 1.0;
+        return out;
+    }
+
+    let p = eye + dir * t;
+    var base = vec3<f32>(sin(uniforms.time), sin(uniforms.time + 2.094), sin(uniforms.time + 4.188)) * 0.4 + 0.6;
+    if p.y < 0.01 {
+        let checker = (floor(p.x) + floor(p.z)) % 2.0;
+        base = vec3<f32>(0.3 + 0.5 * abs(checker));
+    }
+    let light = normalize(vec3<f32>(0.6, 0.8, 0.4));
+    let diffuse = max(dot(normal(p), light), 0.0);
+    let color = mix(base * (0.2 + 0.8 * diffuse), sky, t / FAR);
+
+    out.color = vec4<f32>(color, 1.0);
+    out.depth = t / FAR;
+    return out;
+}
+"#;
+
+// Built-in shaders the arrow keys cycle through, as (name, WGSL source)
+pub const PRESETS: &[(&str, &str)] = &[
+    ("Psychedelic", FRAGMENT_SHADER),
+    ("Plasma", PLASMA_SHADER),
+    ("UV Debug", UV_DEBUG_SHADER),
+    ("Feedback", FEEDBACK_SHADER),
+    ("Raymarch", RAYMARCH_SHADER),
+];
@@ -0,0 +1,119 @@
+use crate::options::{Options, Record};
+use crate::{capture_frame, FragmentSource, Offscreen};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+// Extensions that are encoded to a video with ffmpeg rather than written out
+// as a PNG sequence
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mkv", "mov", "webm"];
+
+// Where recorded frames end up
+enum Sink {
+    Ffmpeg { child: Child, stdin: ChildStdin },
+    Images { dir: PathBuf },
+}
+
+impl Sink {
+    fn open(record: &Record) -> Result<Self, String> {
+        let is_video = record
+            .path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| VIDEO_EXTENSIONS.contains(&ext));
+
+        if !is_video {
+            fs::create_dir_all(&record.path)
+                .map_err(|err| format!("failed to create '{}': {}", record.path.display(), err))?;
+            return Ok(Sink::Images {
+                dir: record.path.clone(),
+            });
+        }
+
+        // Raw RGBA frames are piped straight into ffmpeg's stdin
+        let mut child = Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error"])
+            .args(["-f", "rawvideo", "-pix_fmt", "rgba"])
+            .args(["-s", &format!("{}x{}", record.width, record.height)])
+            .args(["-r", &record.fps.to_string()])
+            .args(["-i", "-", "-pix_fmt", "yuv420p"])
+            .arg(&record.path)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|err| format!("failed to start ffmpeg: {}", err))?;
+        let stdin = child.stdin.take().ok_or("failed to open ffmpeg's stdin")?;
+        Ok(Sink::Ffmpeg { child, stdin })
+    }
+
+    fn write(&mut self, index: u32, pixels: &[u8], record: &Record) -> Result<(), String> {
+        match self {
+            Sink::Ffmpeg { stdin, .. } => stdin
+                .write_all(pixels)
+                .map_err(|err| format!("failed to write to ffmpeg: {}", err)),
+            Sink::Images { dir } => {
+                let path = dir.join(format!("frame_{:05}.png", index + 1));
+                image::save_buffer(
+                    &path,
+                    pixels,
+                    record.width,
+                    record.height,
+                    image::ColorType::Rgba8,
+                )
+                .map_err(|err| format!("failed to write '{}': {}", path.display(), err))
+            }
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        match self {
+            Sink::Ffmpeg { mut child, stdin } => {
+                // Closing stdin tells ffmpeg the stream is over
+                drop(stdin);
+                let status = child
+                    .wait()
+                    .map_err(|err| format!("failed to wait for ffmpeg: {}", err))?;
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(format!("ffmpeg exited with {}", status))
+                }
+            }
+            Sink::Images { .. } => Ok(()),
+        }
+    }
+}
+
+// Render `record.frames` frames of the shader offscreen at a fixed timestep
+pub fn record(
+    options: &Options,
+    record: &Record,
+    fragment_source: &FragmentSource,
+) -> Result<(), String> {
+    let mut offscreen = Offscreen::new(options, fragment_source, record.width, record.height)?;
+    let Offscreen {
+        device,
+        queue,
+        renderer,
+        uniforms,
+        ..
+    } = &mut offscreen;
+    uniforms.delta = 1.0 / record.fps as f32;
+
+    let frames = record.frames;
+    let mut sink = Sink::open(record)?;
+    for frame in 0..frames {
+        uniforms.time = frame as f32 / record.fps as f32 * options.speed;
+        uniforms.frame = frame;
+        renderer.update(queue, uniforms);
+
+        let pixels = capture_frame(device, queue, renderer, record.width, record.height, true)?;
+        sink.write(frame, &pixels, record)?;
+        eprint!("\rRecording frame {}/{}", frame + 1, frames);
+    }
+    eprintln!();
+    sink.finish()?;
+
+    println!("Saved {} frames to '{}'", frames, record.path.display());
+    Ok(())
+}
@@ -0,0 +1,63 @@
+// Appended to a `--shadertoy` shader, after its own code so compile errors
+// keep their line numbers. It fills Shadertoy's inputs in from the uniforms
+// and calls the shader's
+//     fn mainImage(fragColor: ptr<function, vec4<f32>>, fragCoord: vec2<f32>)
+// with `fragCoord` in pixels from the bottom-left, as on Shadertoy.
+//
+// Populated: iResolution (z is 1), iTime, iTimeDelta, iFrame and iMouse,
+// whose xy is the cursor and zw the last click, both from the bottom-left,
+// with zw negated once the button is released. Shadertoy's iChannelTime,
+// iChannelResolution, iDate and iSampleRate aren't provided. The channels
+// are bound as usual, see --channel0.
+const PRELUDE: &str = r#"
+// Shadertoy inputs, added by --shadertoy
+struct ShadertoyUniforms {
+    time: f32,
+    delta: f32,
+    resolution: vec2<f32>,
+    mouse: vec4<f32>,
+    frame: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> shadertoy_uniforms: ShadertoyUniforms;
+
+var<private> iResolution: vec3<f32>;
+var<private> iTime: f32;
+var<private> iTimeDelta: f32;
+var<private> iFrame: i32;
+var<private> iMouse: vec4<f32>;
+
+// Flip a position from the top-left origin the uniforms use, keeping the
+// sign that says whether the button is held
+fn shadertoy_flip(position: vec2<f32>, height: f32) -> vec2<f32> {
+    if all(position == vec2<f32>(0.0)) {
+        return position;
+    }
+    let flipped = vec2<f32>(abs(position.x), height - abs(position.y));
+    return select(flipped, -flipped, position < vec2<f32>(0.0));
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let height = shadertoy_uniforms.resolution.y;
+    iResolution = vec3<f32>(shadertoy_uniforms.resolution, 1.0);
+    iTime = shadertoy_uniforms.time;
+    iTimeDelta = shadertoy_uniforms.delta;
+    iFrame = i32(shadertoy_uniforms.frame);
+    iMouse = vec4<f32>(
+        shadertoy_uniforms.mouse.x,
+        height - shadertoy_uniforms.mouse.y,
+        shadertoy_flip(shadertoy_uniforms.mouse.zw, height),
+    );
+
+    var fragColor = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    mainImage(&fragColor, vec2<f32>(position.x, height - position.y));
+    return fragColor;
+}
+"#;
+
+// A WGSL shader defining `mainImage` with `fs_main` wrapped around it
+pub fn wrap(code: &str) -> String {
+    format!("{}\n{}", code, PRELUDE)
+}
@@ -0,0 +1,297 @@
+use image::imageops::FilterType;
+use std::path::Path;
+
+// A static texture bound to a channel, like Shadertoy's iChannel0 to 3
+pub struct Texture {
+    pub view: wgpu::TextureView,
+}
+
+// Side length of the noise texture, which tiles
+const NOISE_SIZE: u32 = 256;
+
+// Side length of the blue noise texture, which tiles too. Generating it takes
+// time that grows with the square of the texel count, so it's kept small.
+pub const BLUE_NOISE_SIZE: u32 = 64;
+
+// Spread of the filter that measures how clustered the blue noise points are,
+// in texels, and how far out it's applied
+const BLUE_NOISE_SIGMA: f32 = 1.5;
+const BLUE_NOISE_RADIUS: usize = 8;
+
+impl Texture {
+    // Load an image file, optionally with a full mip chain. Colors are treated
+    // as sRGB and alpha is left straight, as image files store it, so shaders
+    // get linear, non-premultiplied values back from `textureSample`.
+    pub fn load(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: &Path,
+        mipmaps: bool,
+    ) -> Result<Self, String> {
+        let image = image::open(path)
+            .map_err(|err| format!("failed to load texture '{}': {}", path.display(), err))?
+            .into_rgba8();
+
+        let levels = if mipmaps {
+            image.width().max(image.height()).ilog2() + 1
+        } else {
+            1
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Channel Texture"),
+            size: wgpu::Extent3d {
+                width: image.width(),
+                height: image.height(),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: levels,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        // Smaller levels are downsampled on the CPU from the full image
+        for level in 0..levels {
+            let width = (image.width() >> level).max(1);
+            let height = (image.height() >> level).max(1);
+            let mip = if level == 0 {
+                image.clone()
+            } else {
+                image::imageops::resize(&image, width, height, FilterType::Triangle)
+            };
+            write_level(queue, &texture, level, &mip);
+        }
+
+        Ok(Self::from_texture(&texture))
+    }
+
+    // A single white texel, bound when no image was given so every shader sees
+    // the same layout
+    pub fn placeholder(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Placeholder Texture"),
+            size: wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        write_level(
+            queue,
+            &texture,
+            0,
+            &image::RgbaImage::from_pixel(1, 1, image::Rgba([255; 4])),
+        );
+        Self::from_texture(&texture)
+    }
+
+    // Uniform white noise with an independent value in each channel. The
+    // values are data rather than colors, so the format isn't sRGB, and the
+    // same seed is used every run so shaders look the same each time.
+    pub fn noise(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Noise Texture"),
+            size: wgpu::Extent3d {
+                width: NOISE_SIZE,
+                height: NOISE_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        // xorshift32
+        let mut state = 0x9e37_79b9_u32;
+        let image = image::RgbaImage::from_fn(NOISE_SIZE, NOISE_SIZE, |_, _| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            image::Rgba(state.to_le_bytes())
+        });
+        write_level(queue, &texture, 0, &image);
+        Self::from_texture(&texture)
+    }
+
+    // Tiling blue noise, the same in every color channel: each value is spread
+    // as far as possible from similar ones, so there are no clumps or low
+    // frequency blotches, which makes it a far smoother grain or dither than
+    // white noise. Like `noise` it's data rather than color.
+    pub fn blue_noise(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Blue Noise Texture"),
+            size: wgpu::Extent3d {
+                width: BLUE_NOISE_SIZE,
+                height: BLUE_NOISE_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let ranks = void_and_cluster(BLUE_NOISE_SIZE as usize);
+        let texels = ranks.len();
+        let image = image::RgbaImage::from_fn(BLUE_NOISE_SIZE, BLUE_NOISE_SIZE, |x, y| {
+            let rank = ranks[(y * BLUE_NOISE_SIZE + x) as usize];
+            let value = (rank * 256 / texels) as u8;
+            image::Rgba([value, value, value, 255])
+        });
+        write_level(queue, &texture, 0, &image);
+        Self::from_texture(&texture)
+    }
+
+    fn from_texture(texture: &wgpu::Texture) -> Self {
+        Self {
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+        }
+    }
+}
+
+// The sampler every channel is read with: repeating, so textures tile, and
+// filtered linearly across texels and mip levels
+pub fn channel_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("Channel Sampler"),
+        address_mode_u: wgpu::AddressMode::Repeat,
+        address_mode_v: wgpu::AddressMode::Repeat,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    })
+}
+
+// Rank every texel of a `size` by `size` torus with Ulichney's void and
+// cluster method. A sparse set of points is first evened out by repeatedly
+// moving the most crowded point to the emptiest spot, then points are taken
+// out from the most crowded and added to the emptiest, each one ranked in
+// the order it went, until every texel has a rank from 0 up.
+fn void_and_cluster(size: usize) -> Vec<usize> {
+    let texels = size * size;
+
+    // How much a point adds to the crowding at each offset from it, out to
+    // where that's negligible, and wrapping around the edges
+    let width = 2 * BLUE_NOISE_RADIUS + 1;
+    let kernel: Vec<f32> = (0..width * width)
+        .map(|offset| {
+            let dx = (offset % width) as f32 - BLUE_NOISE_RADIUS as f32;
+            let dy = (offset / width) as f32 - BLUE_NOISE_RADIUS as f32;
+            (-(dx * dx + dy * dy) / (2.0 * BLUE_NOISE_SIGMA * BLUE_NOISE_SIGMA)).exp()
+        })
+        .collect();
+    let update = |energy: &mut [f32], texel: usize, sign: f32| {
+        let (x, y) = (texel % size, texel / size);
+        for (offset, weight) in kernel.iter().enumerate() {
+            let other_x = (x + size + offset % width - BLUE_NOISE_RADIUS) % size;
+            let other_y = (y + size + offset / width - BLUE_NOISE_RADIUS) % size;
+            energy[other_y * size + other_x] += sign * weight;
+        }
+    };
+    // The most crowded point, or the emptiest spot without one
+    let extreme = |energy: &[f32], points: &[bool], point: bool| {
+        let candidates = energy
+            .iter()
+            .zip(points)
+            .enumerate()
+            .filter(|(_, (_, &is_point))| is_point == point)
+            .map(|(texel, (&energy, _))| (texel, energy));
+        if point {
+            candidates.max_by(|a, b| a.1.total_cmp(&b.1))
+        } else {
+            candidates.min_by(|a, b| a.1.total_cmp(&b.1))
+        }
+        .map(|(texel, _)| texel)
+        .unwrap()
+    };
+
+    // Start from a tenth of the texels chosen at random, with a fixed seed so
+    // the texture is the same every run
+    let mut points = vec![false; texels];
+    let mut energy = vec![0.0; texels];
+    let mut state = 0x2545_f491_u32;
+    let mut count = 0;
+    while count < texels / 10 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let texel = state as usize % texels;
+        if !points[texel] {
+            points[texel] = true;
+            update(&mut energy, texel, 1.0);
+            count += 1;
+        }
+    }
+
+    // Even the points out until the most crowded one is already the best
+    // place for itself
+    loop {
+        let cluster = extreme(&energy, &points, true);
+        points[cluster] = false;
+        update(&mut energy, cluster, -1.0);
+        let void = extreme(&energy, &points, false);
+        points[void] = true;
+        update(&mut energy, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0; texels];
+
+    // The initial points get the lowest ranks, the most crowded last
+    let mut removed = points.clone();
+    let mut removed_energy = energy.clone();
+    for rank in (0..count).rev() {
+        let cluster = extreme(&removed_energy, &removed, true);
+        removed[cluster] = false;
+        update(&mut removed_energy, cluster, -1.0);
+        ranks[cluster] = rank;
+    }
+
+    // The rest fill the emptiest spots in turn
+    for rank in count..texels {
+        let void = extreme(&energy, &points, false);
+        points[void] = true;
+        update(&mut energy, void, 1.0);
+        ranks[void] = rank;
+    }
+    ranks
+}
+
+fn write_level(queue: &wgpu::Queue, texture: &wgpu::Texture, level: u32, image: &image::RgbaImage) {
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: level,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        image,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * image.width()),
+            rows_per_image: Some(image.height()),
+        },
+        wgpu::Extent3d {
+            width: image.width(),
+            height: image.height(),
+            depth_or_array_layers: 1,
+        },
+    );
+}
@@ -0,0 +1,137 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// Number of frames the GPU time is averaged over
+const SAMPLES: usize = 60;
+
+// Measures how long the shader pass takes on the GPU with a pair of timestamp
+// queries. Results are read back asynchronously, and frames that start while
+// the previous readback is still in flight simply go unmeasured instead of
+// stalling on it.
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    // Nanoseconds per timestamp tick
+    period: f32,
+    // Set by the map callback once the readback buffer can be read
+    mapped: Arc<AtomicBool>,
+    // Whether the readback buffer is waiting on a submitted frame
+    pending: bool,
+    // Whether timestamps were recorded for the frame being built
+    recorded: bool,
+    samples: VecDeque<f32>,
+}
+
+impl GpuTimer {
+    // `None` if the device was created without timestamp queries
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Timestamp Queries"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+        let size = 2 * std::mem::size_of::<u64>() as u64;
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp Resolve Buffer"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp Readback Buffer"),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            period: queue.get_timestamp_period(),
+            mapped: Arc::new(AtomicBool::new(false)),
+            pending: false,
+            recorded: false,
+            samples: VecDeque::with_capacity(SAMPLES),
+        })
+    }
+
+    // Record the start of the measured work
+    pub fn begin(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if !self.pending {
+            encoder.write_timestamp(&self.query_set, 0);
+            self.recorded = true;
+        }
+    }
+
+    // Record the end of the measured work and copy both timestamps out
+    pub fn end(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if !self.recorded {
+            return;
+        }
+        encoder.write_timestamp(&self.query_set, 1);
+        encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            self.resolve_buffer.size(),
+        );
+    }
+
+    // Start reading back the timestamps once the frame has been submitted
+    pub fn submitted(&mut self) {
+        if !self.recorded {
+            return;
+        }
+        self.recorded = false;
+        self.pending = true;
+
+        let mapped = self.mapped.clone();
+        self.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                if result.is_ok() {
+                    mapped.store(true, Ordering::Release);
+                }
+            });
+    }
+
+    // Pick up a finished readback, if there is one, and return its time in
+    // milliseconds. Map callbacks only run while the device is polled.
+    pub fn collect(&mut self) -> Option<f32> {
+        if !self.mapped.swap(false, Ordering::Acquire) {
+            return None;
+        }
+
+        let elapsed = {
+            let data = self.readback_buffer.slice(..).get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+            timestamps[1].saturating_sub(timestamps[0]) as f32 * self.period
+        };
+        self.readback_buffer.unmap();
+        self.pending = false;
+
+        let elapsed = elapsed / 1_000_000.0;
+        if self.samples.len() == SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(elapsed);
+        Some(elapsed)
+    }
+
+    // Average GPU time of the last `SAMPLES` measured frames, in milliseconds
+    pub fn average_ms(&self) -> Option<f32> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        Some(self.samples.iter().sum::<f32>() / self.samples.len() as f32)
+    }
+}
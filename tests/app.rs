@@ -0,0 +1,43 @@
+// Drives `ShaderApp` the way examples/embed.rs does. It needs a window, so
+// machines without a display skip it.
+#![cfg(target_os = "linux")]
+
+use shader::{Options, ShaderApp};
+use winit::event_loop::EventLoopBuilder;
+use winit::window::WindowBuilder;
+
+#[test]
+fn draws_into_a_window() {
+    if std::env::var_os("DISPLAY").is_none() && std::env::var_os("WAYLAND_DISPLAY").is_none() {
+        eprintln!("No display, skipping the window test");
+        return;
+    }
+
+    // Tests don't run on the main thread
+    let mut builder = EventLoopBuilder::new();
+    winit::platform::x11::EventLoopBuilderExtX11::with_any_thread(&mut builder, true);
+    let event_loop = builder.build();
+    let window = || {
+        WindowBuilder::new()
+            .with_inner_size(winit::dpi::PhysicalSize::new(64, 36))
+            .build(&event_loop)
+            .unwrap()
+    };
+
+    let missing = Options {
+        shader_path: Some("does-not-exist.wgsl".into()),
+        ..Options::default()
+    };
+    assert!(ShaderApp::new(&event_loop, window(), missing).is_err());
+
+    let mut app = ShaderApp::new(&event_loop, window(), Options::default()).unwrap();
+
+    for frame in 0..3 {
+        app.update(frame as f32 / 60.0).unwrap();
+        app.render().unwrap();
+    }
+    let size = app.window().inner_size();
+    app.resize(size.width, size.height);
+    app.update(0.5).unwrap();
+    app.render().unwrap();
+}